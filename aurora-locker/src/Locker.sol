@@ -8,10 +8,28 @@ import "./AuroraSdk.sol";
 string constant ERR_METHOD_NOT_IMPLEMENTED = "ERR_METHOD_NOT_IMPLEMENTED";
 // TODO: Determine proper values for gas.
 uint64 constant ON_DEPOSIT_NEAR_GAS = 25_000_000_000_000;
+// Extra gas for the factory to upgrade an outdated token before calling it, when lazy
+// token upgrades are enabled. Without it the token is left as is.
+uint64 constant LAZY_UPGRADE_NEAR_GAS = 70_000_000_000_000;
 // TODO: Determine proper values for gas.
 uint64 constant CREATE_NEAR_GAS = 50_000_000_000_000;
 // TODO: Determine proper values for gas.
 uint64 constant DEPOSIT_CALLBACK_NEAR_GAS = 15_000_000_000_000;
+// TODO: Determine proper values for gas.
+uint64 constant ON_DEPOSIT_BATCH_NEAR_GAS = 15_000_000_000_000;
+// TODO: Determine proper values for gas.
+uint64 constant ON_DEPOSIT_BATCH_ITEM_NEAR_GAS = 10_000_000_000_000;
+// TODO: Determine proper values for gas.
+uint64 constant DEPOSIT_BATCH_CALLBACK_ITEM_NEAR_GAS = 5_000_000_000_000;
+// Gas limit of the NEAR transaction that executes the scheduled promises.
+uint64 constant MAX_NEAR_GAS = 300_000_000_000_000;
+// TODO: Determine proper values for gas.
+// Gas used by the XCC router of the locker to execute the scheduled promises.
+uint64 constant XCC_ROUTER_NEAR_GAS = 30_000_000_000_000;
+// Both `on_deposit_batch` and its callback grow with the number of deposits, and
+// together with the router they must fit in the gas limit of the transaction.
+uint256 constant MAX_DEPOSIT_BATCH_SIZE = (MAX_NEAR_GAS - XCC_ROUTER_NEAR_GAS - ON_DEPOSIT_BATCH_NEAR_GAS
+    - DEPOSIT_CALLBACK_NEAR_GAS) / (ON_DEPOSIT_BATCH_ITEM_NEAR_GAS + DEPOSIT_BATCH_CALLBACK_ITEM_NEAR_GAS);
 uint64 constant ON_UPDATE_TOKEN_METADATA = 3_000_000_000_000;
 // TODO: Determine proper values for gas.
 uint64 constant STORAGE_DEPOSIT_NEAR_GAS = 5_000_000_000_000;
@@ -31,7 +49,10 @@ contract Locker {
     using AuroraSdk for NEAR;
     using AuroraSdk for PromiseWithCallback;
     using AuroraSdk for PromiseCreateArgs;
+    using Borsh for Borsh.Data;
     using Codec for bytes;
+    using Codec for uint32;
+    using Codec for uint64;
     using Codec for uint128;
     using Codec for uint8;
 
@@ -46,6 +67,18 @@ contract Locker {
     /// An address is present in this mapping with a non-zero value if the corresponding NEAR
     /// token has been created.
     mapping(IERC20 => uint256) public registeredTokens;
+    /// Nonce of the last message sent to the factory. Every message carries a new
+    /// nonce, so the factory can reject messages that are delivered twice.
+    uint64 public nonce;
+
+    /// Emitted when tokens are locked to be minted on NEAR. `transferId` is the nonce of
+    /// the message sent to the factory, which is included in the memo of the minted tokens
+    /// as `deposit:<transferId>`.
+    event Deposit(IERC20 indexed token, address indexed sender, string receiverId, uint128 amount, uint64 transferId);
+    /// Emitted when tokens burnt on NEAR are unlocked. `transferId` is assigned by the token on
+    /// NEAR and included in the memo of its burn as `withdraw:<transferId>`. It is unique per
+    /// token and independent from the transfer ids of deposits.
+    event Withdraw(IERC20 indexed token, address indexed receiver, uint256 amount, uint64 transferId);
 
     constructor(string memory factoryAccountId_, IERC20 wNEAR) {
         factoryAccountId = factoryAccountId_;
@@ -71,7 +104,11 @@ contract Locker {
         // Require user to cover the cost of creating a new token by
         // attaching a balance of `NEW_TOKEN_DEPOSIT_COST`.
         PromiseCreateArgs memory createOnNear = near.call(
-            factoryAccountId, "create_token", abi.encodePacked(token), NEW_TOKEN_DEPOSIT_COST, CREATE_NEAR_GAS
+            factoryAccountId,
+            "create_token",
+            abi.encodePacked(token, nextNonce().encodeU64()),
+            NEW_TOKEN_DEPOSIT_COST,
+            CREATE_NEAR_GAS
         );
 
         createOnNear.transact();
@@ -111,14 +148,17 @@ contract Locker {
         // First transfer the tokens from the caller to the locker contract.
         token.transferFrom(msg.sender, address(this), amount);
 
+        uint64 transferId = nextNonce();
+        emit Deposit(token, msg.sender, receiverId, amount, transferId);
+
         // Issue a call to the factory on NEAR factory to mint the same amount
         // of tokens for the receiverId on NEAR for this token.
         PromiseCreateArgs memory mintOnNear = near.call(
             factoryAccountId,
             "on_deposit",
-            abi.encodePacked(token, bytes(receiverId).encode(), amount.encodeU128()),
+            abi.encodePacked(token, bytes(receiverId).encode(), amount.encodeU128(), transferId.encodeU64()),
             0,
-            ON_DEPOSIT_NEAR_GAS
+            ON_DEPOSIT_NEAR_GAS + LAZY_UPGRADE_NEAR_GAS
         );
 
         // Prepare callback to return tokens to the sender if the call to
@@ -149,6 +189,73 @@ contract Locker {
         }
     }
 
+    /// Deposit many tokens at once with a single call to the factory. Each
+    /// deposit is the same as in `deposit`, but the factory processes them
+    /// independently. Deposits that fail are refunded to the sender, while the
+    /// others are minted. At most `MAX_DEPOSIT_BATCH_SIZE` deposits fit in a batch.
+    function depositBatch(IERC20[] memory tokens, string[] memory receiverIds, uint128[] memory amounts) public {
+        require(tokens.length == receiverIds.length && tokens.length == amounts.length, "ERR_LENGTH_MISMATCH");
+        require(tokens.length > 0 && tokens.length <= MAX_DEPOSIT_BATCH_SIZE, "ERR_INVALID_BATCH_SIZE");
+
+        uint64 transferId = nextNonce();
+
+        // Borsh encoded vector of (token, receiverId, amount).
+        bytes memory deposits = abi.encodePacked(uint32(tokens.length).encodeU32());
+        for (uint256 i = 0; i < tokens.length; i++) {
+            require(registeredTokens[tokens[i]] > 0, "ERR_TOKEN_NOT_FOUND");
+
+            tokens[i].transferFrom(msg.sender, address(this), amounts[i]);
+            emit Deposit(tokens[i], msg.sender, receiverIds[i], amounts[i], transferId);
+
+            deposits = abi.encodePacked(deposits, tokens[i], bytes(receiverIds[i]).encode(), amounts[i].encodeU128());
+        }
+
+        uint64 itemCount = uint64(tokens.length);
+        PromiseCreateArgs memory mintOnNear = near.call(
+            factoryAccountId,
+            "on_deposit_batch",
+            abi.encodePacked(deposits, transferId.encodeU64()),
+            0,
+            ON_DEPOSIT_BATCH_NEAR_GAS + ON_DEPOSIT_BATCH_ITEM_NEAR_GAS * itemCount
+        );
+
+        // Prepare callback to return the tokens of failed deposits to the sender.
+        PromiseCreateArgs memory callback = near.auroraCall(
+            address(this),
+            abi.encodeWithSelector(this.depositBatchCallback.selector, tokens, msg.sender, amounts),
+            0,
+            DEPOSIT_CALLBACK_NEAR_GAS + DEPOSIT_BATCH_CALLBACK_ITEM_NEAR_GAS * itemCount
+        );
+
+        mintOnNear.then(callback).lazy_transact();
+    }
+
+    /// Callback to return tokens to the sender for the deposits of a batch that
+    /// failed. The factory returns a borsh encoded vector with the success of
+    /// each deposit. If the whole call failed, all tokens are returned. This
+    /// method can only be called by the representative NEAR account of this
+    /// contract.
+    function depositBatchCallback(IERC20[] memory tokens, address sender, uint128[] memory amounts) public {
+        require(msg.sender == selfReprsentativeImplicitAddress, "ERR_ACCESS_DENIED");
+
+        PromiseResult memory result = AuroraSdk.promiseResult(0);
+        if (result.status != PromiseResultStatus.Successful) {
+            for (uint256 i = 0; i < tokens.length; i++) {
+                tokens[i].transfer(sender, amounts[i]);
+            }
+            return;
+        }
+
+        Borsh.Data memory borsh = Borsh.from(result.output);
+        uint32 length = borsh.decodeU32();
+        require(length == tokens.length, "ERR_LENGTH_MISMATCH");
+        for (uint256 i = 0; i < tokens.length; i++) {
+            if (!borsh.decodeBool()) {
+                tokens[i].transfer(sender, amounts[i]);
+            }
+        }
+    }
+
     /// Finish the transfer of tokens from NEAR to Aurora.
     ///
     /// This function CAN only be called from the factory contract. Tokens
@@ -156,12 +263,49 @@ contract Locker {
     /// It is important that this function MUST never fail. In particular the
     /// amount to be withdrawn WILL be owned by the contract, since it was
     /// deposited before during a transfer.
-    function withdraw(IERC20 token, address receiver, uint256 amount) public {
+    ///
+    /// `transferId` is assigned by the token on NEAR to trace the transfer
+    /// across both chains.
+    function withdraw(IERC20 token, address receiver, uint256 amount, uint64 transferId) public {
         // Only the factory contract can call this method.
         require(msg.sender == factoryImplicitAddress, "ERR_ACCESS_DENIED");
 
         // Transfer the tokens to the receiver.
         token.transfer(receiver, amount);
+
+        emit Withdraw(token, receiver, amount, transferId);
+    }
+
+    /// Batched version of `withdraw`, used by the factory to unlock several
+    /// withdrawals of the same token in a single call. Each transfer is tried on
+    /// its own, so a failing transfer doesn't revert the rest of the batch.
+    ///
+    /// Returns whether each withdrawal was unlocked. The factory holds the failed
+    /// ones as pending withdrawals, since their tokens are already burnt on NEAR.
+    function withdrawBatch(
+        IERC20 token,
+        address[] memory receivers,
+        uint256[] memory amounts,
+        uint64[] memory transferIds
+    ) public returns (bool[] memory results) {
+        // Only the factory contract can call this method.
+        require(msg.sender == factoryImplicitAddress, "ERR_ACCESS_DENIED");
+        require(
+            receivers.length == amounts.length && receivers.length == transferIds.length, "ERR_INVALID_BATCH"
+        );
+
+        results = new bool[](receivers.length);
+        for (uint256 i = 0; i < receivers.length; i++) {
+            try token.transfer(receivers[i], amounts[i]) returns (bool success) {
+                results[i] = success;
+            } catch {
+                results[i] = false;
+            }
+
+            if (results[i]) {
+                emit Withdraw(token, receivers[i], amounts[i], transferIds[i]);
+            }
+        }
     }
 
     /// Fetch the current metadata of the specified ERC20 token and updates
@@ -182,15 +326,23 @@ contract Locker {
         PromiseCreateArgs memory updateMetadataOnNear = near.call(
             factoryAccountId,
             "update_token_metadata",
-            abi.encodePacked(token, bytes(name).encode(), bytes(symbol).encode(), decimals.encodeU8()),
+            abi.encodePacked(
+                token, bytes(name).encode(), bytes(symbol).encode(), decimals.encodeU8(), nextNonce().encodeU64()
+            ),
             0,
-            ON_UPDATE_TOKEN_METADATA
+            ON_UPDATE_TOKEN_METADATA + LAZY_UPGRADE_NEAR_GAS
         );
 
         // Schedule the promise.
         updateMetadataOnNear.transact();
     }
 
+    /// Increment the nonce and return its new value.
+    function nextNonce() internal returns (uint64) {
+        nonce += 1;
+        return nonce;
+    }
+
     /// Transfer ERC20 tokens from Aurora to NEAR chain and execute a
     /// function call.
     ///
@@ -1,11 +1,14 @@
 use near_sdk::borsh;
 use near_sdk::ext_contract;
 
-use crate::types::CallArgs;
+use crate::types::{CallArgs, ViewCallArgs};
 
 #[ext_contract(ext_aurora)]
 pub trait Aurora {
     fn call(&mut self, #[serializer(borsh)] args: CallArgs);
+
+    /// Returns borsh serialized [`crate::TransactionStatus`].
+    fn view(&self, #[serializer(borsh)] args: ViewCallArgs);
 }
 
 pub fn call_args(to: crate::Address, input: Vec<u8>) -> CallArgs {
@@ -15,3 +18,12 @@ pub fn call_args(to: crate::Address, input: Vec<u8>) -> CallArgs {
         input,
     })
 }
+
+pub fn view_args(sender: crate::Address, to: crate::Address, input: Vec<u8>) -> ViewCallArgs {
+    ViewCallArgs {
+        sender,
+        address: to,
+        amount: Default::default(),
+        input,
+    }
+}
@@ -0,0 +1,30 @@
+use near_sdk::env;
+use serde::Serialize;
+
+/// Standard name used in all custom NEP-297 events emitted by the connector contracts.
+pub const EVENT_STANDARD: &str = "aurora-erc20-connector";
+/// Version of the custom events standard.
+pub const EVENT_STANDARD_VERSION: &str = "1.0.0";
+
+/// NEP-297 event envelope. See:
+/// <https://github.com/near/NEPs/blob/master/specs/Standards/EventsFormat.md>
+#[derive(Serialize)]
+struct NearEvent<'a, T: Serialize> {
+    standard: &'a str,
+    version: &'a str,
+    event: &'a str,
+    data: [&'a T; 1],
+}
+
+/// Logs `data` as a NEP-297 event with the connector standard and the given event name.
+pub fn emit<T: Serialize>(event: &str, data: &T) {
+    let event = NearEvent {
+        standard: EVENT_STANDARD,
+        version: EVENT_STANDARD_VERSION,
+        event,
+        data: [data],
+    };
+    let json = near_sdk::serde_json::to_string(&event)
+        .unwrap_or_else(|_| env::panic_str("ERR_SERIALIZE_EVENT"));
+    env::log_str(&format!("EVENT_JSON:{}", json));
+}
@@ -1,4 +1,5 @@
 pub mod aurora;
+pub mod events;
 pub mod types;
 
 pub use types::*;
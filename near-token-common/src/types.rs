@@ -1,4 +1,6 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::AccountId;
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 
@@ -33,6 +35,43 @@ impl From<CallArgs> for FunctionCallArgs {
 
 pub type WeiU256 = [u8; 32];
 
+/// Borsh-encoded parameters for the engine `view` method.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct ViewCallArgs {
+    pub sender: Address,
+    pub address: Address,
+    pub amount: WeiU256,
+    pub input: Vec<u8>,
+}
+
+/// Result of an EVM transaction, as returned by the engine `view` method.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub enum TransactionStatus {
+    Succeed(Vec<u8>),
+    Revert(Vec<u8>),
+    OutOfGas,
+    OutOfFund,
+    OutOfOffset,
+    CallTooDeep,
+}
+
+/// Result of an EVM transaction, as returned by the engine `call` method.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct SubmitResult {
+    pub version: u8,
+    pub status: TransactionStatus,
+    pub gas_used: u64,
+    pub logs: Vec<ResultLog>,
+}
+
+/// Log emitted by an EVM transaction.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct ResultLog {
+    pub address: Address,
+    pub topics: Vec<[u8; 32]>,
+    pub data: Vec<u8>,
+}
+
 #[derive(Serialize, Deserialize, BorshDeserialize, BorshSerialize, Debug, Clone)]
 pub struct Address(#[serde(with = "address_serde_hex")] pub [u8; 20]);
 
@@ -70,6 +109,16 @@ impl From<[u8; 20]> for Address {
     }
 }
 
+/// Address on Aurora derived from a NEAR account id, i.e. the last 20 bytes of the
+/// keccak256 hash of the account id. It matches the address used by the engine for
+/// transactions submitted by `account_id` on NEAR.
+pub fn near_account_to_evm_address(account_id: &[u8]) -> Address {
+    let hash = near_sdk::env::keccak256(account_id);
+    let mut address = [0u8; 20];
+    address.copy_from_slice(&hash[12..]);
+    Address(address)
+}
+
 /// Similar to FungibleTokenMetadata. However, all fields are optional such that the user
 /// can specify any subset of it to be updated.
 #[derive(Deserialize, Serialize, Default)]
@@ -81,3 +130,84 @@ pub struct UpdateFungibleTokenMetadata {
     pub reference_hash: Option<near_sdk::json_types::Base64VecU8>,
     pub decimals: Option<u8>,
 }
+
+/// Provenance of a NEP-141 token deployed by the factory, i.e. which ERC-20 it represents
+/// and which contracts are involved in bridging it.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct BridgedTokenInfo {
+    /// Address of the ERC-20 token on Aurora.
+    pub erc20_address: Address,
+    /// Account id of the Aurora engine.
+    pub aurora: AccountId,
+    /// Address of the locker contract on Aurora.
+    pub locker: Address,
+    /// Account id of the factory that deployed the token.
+    pub factory: AccountId,
+    /// Version of the token binary, as assigned by the factory. It is updated when the
+    /// factory upgrades the token.
+    pub binary_version: u32,
+}
+
+/// Bridging fee minted to the fee collector of the factory.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct BridgeFee {
+    pub collector_id: AccountId,
+    pub amount: U128,
+}
+
+/// Largest fee share, i.e. the whole amount, in basis points.
+pub const MAX_FEE_BPS: u16 = 10_000;
+
+/// Fee charged for bridging a token in either direction. The fee is a share of the
+/// amount in basis points, but never less than `min_fee`.
+#[derive(BorshSerialize, BorshDeserialize, Deserialize, Serialize, Debug, Clone)]
+pub struct FeeSchedule {
+    pub fee_bps: u16,
+    pub min_fee: U128,
+}
+
+impl FeeSchedule {
+    /// Fee charged for bridging `amount` tokens. It never exceeds `amount`.
+    pub fn fee(&self, amount: u128) -> u128 {
+        let fee_bps = u128::from(self.fee_bps);
+        let max_fee_bps = u128::from(MAX_FEE_BPS);
+        // Equivalent to `amount * fee_bps / max_fee_bps` without overflowing.
+        let fee = amount / max_fee_bps * fee_bps + amount % max_fee_bps * fee_bps / max_fee_bps;
+        fee.max(self.min_fee.0).min(amount)
+    }
+}
+
+/// Fee charged by a token on withdrawals. It is transferred from the sender to the
+/// fee collector of the factory and only the remaining amount is burnt.
+#[derive(BorshSerialize, BorshDeserialize, Deserialize, Serialize, Debug, Clone)]
+pub struct WithdrawFee {
+    pub collector_id: AccountId,
+    pub schedule: FeeSchedule,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::near_account_to_evm_address;
+
+    #[test]
+    fn test_near_account_to_evm_address() {
+        for account_id in ["alice.near", "token.factory.aurora", "a", ""] {
+            let expected =
+                aurora_engine_sdk::types::near_account_to_evm_address(account_id.as_bytes());
+            assert_eq!(
+                near_account_to_evm_address(account_id.as_bytes()).0,
+                expected.raw().0
+            );
+        }
+    }
+
+    #[test]
+    fn test_near_account_to_evm_address_implicit_account() {
+        let account_id = "f".repeat(64);
+        let expected = aurora_engine_sdk::types::near_account_to_evm_address(account_id.as_bytes());
+        assert_eq!(
+            near_account_to_evm_address(account_id.as_bytes()).0,
+            expected.raw().0
+        );
+    }
+}
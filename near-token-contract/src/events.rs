@@ -0,0 +1,174 @@
+use near_contract_standards::fungible_token::metadata::FungibleTokenMetadata;
+use near_sdk::serde::Serialize;
+use near_sdk::serde_json::{self, Value};
+use near_sdk::AccountId;
+use near_token_common::events;
+
+/// Data to log when the super-admin overrides frozen decimals. To log this event,
+/// call [`.emit()`](FtDecimalsOverride::emit).
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtDecimalsOverride<'a> {
+    pub old_decimals: u8,
+    pub new_decimals: u8,
+    pub updated_by: &'a AccountId,
+}
+
+impl FtDecimalsOverride<'_> {
+    pub fn emit(self) {
+        events::emit("ft_decimals_override", &self)
+    }
+}
+
+/// Old and new value of a single metadata field.
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtMetadataFieldChange {
+    pub field: &'static str,
+    pub old_value: Value,
+    pub new_value: Value,
+}
+
+/// Data to log when the token metadata changes. To log this event, call
+/// [`.emit()`](FtMetadataUpdate::emit).
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtMetadataUpdate<'a> {
+    pub updated_by: &'a AccountId,
+    pub changes: Vec<FtMetadataFieldChange>,
+}
+
+impl<'a> FtMetadataUpdate<'a> {
+    /// Computes the fields that differ between `old` and `new`. Returns `None` if
+    /// nothing changed, in which case no event should be emitted.
+    pub fn from_diff(
+        updated_by: &'a AccountId,
+        old: &FungibleTokenMetadata,
+        new: &FungibleTokenMetadata,
+    ) -> Option<Self> {
+        let mut changes = Vec::new();
+
+        macro_rules! push_if_changed {
+            ($field_name:ident) => {
+                if old.$field_name != new.$field_name {
+                    changes.push(FtMetadataFieldChange {
+                        field: stringify!($field_name),
+                        old_value: serde_json::to_value(&old.$field_name).unwrap(),
+                        new_value: serde_json::to_value(&new.$field_name).unwrap(),
+                    });
+                }
+            };
+        }
+
+        push_if_changed!(spec);
+        push_if_changed!(name);
+        push_if_changed!(symbol);
+        push_if_changed!(icon);
+        push_if_changed!(reference);
+        push_if_changed!(reference_hash);
+        push_if_changed!(decimals);
+
+        if changes.is_empty() {
+            None
+        } else {
+            Some(Self {
+                updated_by,
+                changes,
+            })
+        }
+    }
+
+    pub fn emit(self) {
+        events::emit("ft_metadata_update", &self)
+    }
+}
+
+/// Data to log when an account is frozen by compliance. To log this event, call
+/// [`.emit()`](AccountFrozen::emit).
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AccountFrozen<'a> {
+    pub account_id: &'a AccountId,
+    pub frozen_by: &'a AccountId,
+}
+
+impl AccountFrozen<'_> {
+    pub fn emit(self) {
+        events::emit("account_frozen", &self)
+    }
+}
+
+/// Data to log when an account is unfrozen by compliance. To log this event, call
+/// [`.emit()`](AccountUnfrozen::emit).
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AccountUnfrozen<'a> {
+    pub account_id: &'a AccountId,
+    pub unfrozen_by: &'a AccountId,
+}
+
+impl AccountUnfrozen<'_> {
+    pub fn emit(self) {
+        events::emit("account_unfrozen", &self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FtMetadataUpdate;
+    use near_contract_standards::fungible_token::metadata::FungibleTokenMetadata;
+    use near_sdk::serde_json::json;
+    use near_sdk::AccountId;
+
+    fn metadata() -> FungibleTokenMetadata {
+        FungibleTokenMetadata {
+            spec: "ft-1.0.0".to_string(),
+            name: "Token".to_string(),
+            symbol: "TKN".to_string(),
+            icon: None,
+            reference: None,
+            reference_hash: None,
+            decimals: 18,
+        }
+    }
+
+    #[test]
+    fn test_metadata_update_from_diff_unchanged() {
+        let updated_by: AccountId = "admin.near".parse().unwrap();
+        assert!(FtMetadataUpdate::from_diff(&updated_by, &metadata(), &metadata()).is_none());
+    }
+
+    #[test]
+    fn test_metadata_update_from_diff_changed() {
+        let updated_by: AccountId = "admin.near".parse().unwrap();
+        let old = metadata();
+        let new = FungibleTokenMetadata {
+            symbol: "NEW".to_string(),
+            icon: Some("data:image/svg+xml,<svg/>".to_string()),
+            decimals: 6,
+            ..metadata()
+        };
+
+        let event = FtMetadataUpdate::from_diff(&updated_by, &old, &new).unwrap();
+        assert_eq!(event.updated_by, &updated_by);
+
+        // Only changed fields are reported, in declaration order.
+        let changes: Vec<_> = event
+            .changes
+            .iter()
+            .map(|change| (change.field, &change.old_value, &change.new_value))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("symbol", &json!("TKN"), &json!("NEW")),
+                ("icon", &json!(null), &json!("data:image/svg+xml,<svg/>")),
+                ("decimals", &json!(18), &json!(6)),
+            ]
+        );
+    }
+}
@@ -4,7 +4,9 @@ use near_sdk::ext_contract;
 pub trait ExtNearTokenFactory {
     fn on_withdraw(
         &mut self,
+        sender_id: near_sdk::AccountId,
         receiver_id: near_token_common::Address,
         amount: near_sdk::json_types::U128,
+        transfer_id: u64,
     );
 }
@@ -1,24 +1,39 @@
+use events::{AccountFrozen, AccountUnfrozen, FtDecimalsOverride, FtMetadataUpdate};
 use ext::ext_near_token_factory;
+use near_contract_standards::fungible_token::core::FungibleTokenCore;
 use near_contract_standards::fungible_token::events::{FtBurn, FtMint};
 use near_contract_standards::fungible_token::metadata::{
     FungibleTokenMetadata, FungibleTokenMetadataProvider,
 };
+use near_contract_standards::fungible_token::resolver::FungibleTokenResolver;
 use near_contract_standards::fungible_token::FungibleToken;
+use near_contract_standards::storage_management::{
+    StorageBalance, StorageBalanceBounds, StorageManagement,
+};
 use near_plugins::{access_control, access_control_any, AccessControlRole, AccessControllable};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::UnorderedSet;
 use near_sdk::json_types::U128;
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::{
-    assert_self, env, near_bindgen, BorshStorageKey, PanicOnDefault, Promise, PromiseOrValue,
+    assert_one_yocto, assert_self, env, near_bindgen, BorshStorageKey, PanicOnDefault, Promise,
+    PromiseOrValue,
 };
-use near_sdk::{require, AccountId, Gas};
+use near_sdk::{require, AccountId, Balance, Gas};
 use near_token_common as aurora_sdk;
 
+mod events;
 mod ext;
 
 // TODO: Determine properly what are good gas constants for both of these steps.
 const GAS_FOR_UNLOCKING_TOKENS: Gas = Gas(10_000_000_000_000);
 const GAS_FOR_ON_WITHDRAW: Gas = Gas(10_000_000_000_000 + GAS_FOR_UNLOCKING_TOKENS.0);
+const GAS_FOR_MIGRATE: Gas = Gas(10_000_000_000_000);
+
+/// Version of the layout of [`Contract`] implemented by this binary.
+const CURRENT_STATE_VERSION: StateVersion = StateVersion::V1;
+/// Storage key of the contract state, as written by `near_bindgen`.
+const STATE_KEY: &[u8] = b"STATE";
 
 macro_rules! maybe_update_metadata {
     ($self:ident, $field_name:ident) => {
@@ -39,12 +54,108 @@ macro_rules! maybe_update_optional_metadata {
 #[derive(BorshDeserialize, BorshSerialize, BorshStorageKey)]
 enum StorageKeys {
     FungibleToken,
+    FrozenAccounts,
+    StateVersion,
+}
+
+/// Layouts of [`Contract`] across binary versions. The version of the stored state
+/// is kept under its own storage key, so the state can be decoded with the right
+/// layout in [`Contract::migrate`]. When the layout changes, a new variant is added
+/// and the previous layout is kept to decode old state.
+#[derive(BorshDeserialize, BorshSerialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateVersion {
+    /// Layout of the first tokens, see [`ContractV0`]. They didn't store a state
+    /// version.
+    V0,
+    /// Layout of [`Contract`].
+    V1,
+}
+
+/// Layout of [`Contract`] in the first token binary, which didn't know its provenance
+/// nor track bridged amounts.
+///
+/// The access control state follows these fields and has the same layout as in
+/// [`Contract`], so it is kept encoded and decoded with the current type.
+struct ContractV0 {
+    factory: AccountId,
+    token: FungibleToken,
+    metadata: FungibleTokenMetadata,
+    /// Encoded access control state.
+    acl: Vec<u8>,
+}
+
+impl ContractV0 {
+    fn read() -> Self {
+        let state = env::storage_read(STATE_KEY)
+            .unwrap_or_else(|| env::panic_str("Failed to read contract state"));
+        let mut state = state.as_slice();
+        let (factory, token, metadata) = BorshDeserialize::deserialize(&mut state)
+            .unwrap_or_else(|_| env::panic_str("Failed to read contract state"));
+        Self {
+            factory,
+            token,
+            metadata,
+            acl: state.to_vec(),
+        }
+    }
+
+    /// Convert to the current layout. The provenance, which the first binary didn't
+    /// store, is provided by the factory. The whole supply is accounted as bridged in,
+    /// and decimals are frozen if the metadata was already synced from Aurora.
+    fn into_contract(self, info: aurora_sdk::BridgedTokenInfo) -> Contract {
+        require!(
+            info.factory == self.factory,
+            "Bridged token info is from another factory"
+        );
+        Contract {
+            factory: self.factory,
+            erc20_address: info.erc20_address,
+            aurora: info.aurora,
+            locker: info.locker,
+            binary_version: info.binary_version,
+            decimals_frozen: self.metadata.decimals != 0 || !self.metadata.symbol.is_empty(),
+            total_bridged_in: self.token.total_supply,
+            token: self.token,
+            metadata: self.metadata,
+            frozen_accounts: UnorderedSet::new(StorageKeys::FrozenAccounts),
+            unregister_refund_address: None,
+            withdraw_fee: None,
+            total_bridged_out: 0,
+            total_reminted: 0,
+            total_refunded: 0,
+            total_burned: 0,
+            next_transfer_id: 0,
+            __acl: BorshDeserialize::try_from_slice(&self.acl)
+                .unwrap_or_else(|_| env::panic_str("Failed to read access control state")),
+        }
+    }
 }
 
 #[derive(AccessControlRole, Deserialize, Serialize, Copy, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub enum AclRole {
     MetadataUpdater,
+    /// Allowed to freeze and unfreeze accounts, see [`Contract::freeze_account`].
+    Compliance,
+}
+
+/// Lifetime counters of tokens bridged through this contract, see
+/// [`Contract::bridge_accounting`].
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BridgeAccounting {
+    pub total_supply: U128,
+    /// Tokens minted with `deposit` and `deposit_call`.
+    pub total_bridged_in: U128,
+    /// Tokens burnt with `withdraw`.
+    pub total_bridged_out: U128,
+    /// Tokens burnt with `withdraw` and minted back by the factory with `remint`,
+    /// because they were not unlocked on Aurora.
+    pub total_reminted: U128,
+    /// Unused tokens from `deposit_call` burnt in `deposit_resolve`.
+    pub total_refunded: U128,
+    /// Tokens burnt in `ft_resolve_transfer` because the sender was unregistered.
+    pub total_burned: U128,
 }
 
 #[access_control(role_type(AclRole))]
@@ -53,10 +164,43 @@ pub enum AclRole {
 pub struct Contract {
     /// Account id of the factory determined at deployment time.
     factory: AccountId,
+    /// Address of the ERC-20 token on Aurora this token represents.
+    erc20_address: aurora_sdk::Address,
+    /// Account id of the Aurora engine.
+    aurora: AccountId,
+    /// Address of the locker contract on Aurora.
+    locker: aurora_sdk::Address,
+    /// Version of the token binary, as assigned by the factory. It is updated by
+    /// `migrate` when the factory upgrades the token.
+    binary_version: u32,
     /// Interface that implements NEP-141 Fungible Token standard.
     token: FungibleToken,
     /// Metadata for the token.
     metadata: FungibleTokenMetadata,
+    /// Whether `decimals` can still be changed through [`Self::update_metadata`]. It
+    /// becomes `true` after the first metadata update that sets decimals.
+    decimals_frozen: bool,
+    /// Accounts that are not allowed to transfer or withdraw tokens.
+    frozen_accounts: UnorderedSet<AccountId>,
+    /// Aurora address that receives the balance of accounts force-unregistered with
+    /// `storage_unregister`. If not set, force-unregistering a non-zero balance fails.
+    unregister_refund_address: Option<aurora_sdk::Address>,
+    /// Fee charged on withdrawals, set by the factory.
+    withdraw_fee: Option<aurora_sdk::WithdrawFee>,
+    /// Lifetime amount of tokens minted from Aurora.
+    total_bridged_in: Balance,
+    /// Lifetime amount of tokens burnt to be unlocked on Aurora.
+    total_bridged_out: Balance,
+    /// Lifetime amount of burnt tokens minted back because they were not unlocked on
+    /// Aurora, see [`Self::remint`].
+    total_reminted: Balance,
+    /// Lifetime amount of tokens refunded to Aurora from `deposit_call`.
+    total_refunded: Balance,
+    /// Lifetime amount of tokens burnt when resolving a transfer to an unregistered
+    /// sender. These tokens remain locked on Aurora.
+    total_burned: Balance,
+    /// Transfer id assigned to the next withdrawal.
+    next_transfer_id: u64,
 }
 
 // TODO: Pausable methods.
@@ -70,21 +214,46 @@ impl Contract {
     /// Method is payable since the factory needs to pay the storage to be
     /// registered automatically.
     ///
+    /// The factory provides the provenance of the token, which is exposed by
+    /// [`Self::bridged_token_info`].
+    ///
     ///Optionally an account can be provided that is made access control super
     ///admin. If `super_admin` is `None`, then the factory itself is made super
     ///admin.
     ///
     ///It grants [`AclRole::MetadataUpdater`] to the factory to enable a
     ///trustless workflow for metadata updates, see [`Self::update_metadata`].
+    ///The role is also granted to `metadata_updaters`, if provided.
     #[init]
     #[payable]
-    pub fn new(super_admin: Option<AccountId>) -> Self {
+    pub fn new(
+        super_admin: Option<AccountId>,
+        erc20_address: aurora_sdk::Address,
+        aurora: AccountId,
+        locker: aurora_sdk::Address,
+        binary_version: u32,
+        metadata_updaters: Option<Vec<AccountId>>,
+    ) -> Self {
         let factory = env::predecessor_account_id();
 
         let mut contract = Self {
             factory: factory.clone(),
+            erc20_address,
+            aurora,
+            locker,
+            binary_version,
             token: FungibleToken::new(StorageKeys::FungibleToken),
             metadata: default_metadata(),
+            decimals_frozen: false,
+            frozen_accounts: UnorderedSet::new(StorageKeys::FrozenAccounts),
+            unregister_refund_address: None,
+            withdraw_fee: None,
+            total_bridged_in: 0,
+            total_bridged_out: 0,
+            total_reminted: 0,
+            total_refunded: 0,
+            total_burned: 0,
+            next_transfer_id: 0,
             __acl: Default::default(),
         };
 
@@ -103,6 +272,12 @@ impl Contract {
                 .grant_role_unchecked(AclRole::MetadataUpdater, &factory),
             "Failed to grant role to factory",
         );
+        for account_id in metadata_updaters.unwrap_or_default() {
+            contract
+                .__acl
+                .grant_role_unchecked(AclRole::MetadataUpdater, &account_id);
+        }
+        write_state_version(CURRENT_STATE_VERSION);
 
         contract
     }
@@ -111,14 +286,31 @@ impl Contract {
     /// the total supply since new tokens are minted. This method MUST be
     /// executed only if the predecessor account id is the factory.
     ///
+    /// If `fee` is provided, its amount is minted to the fee collector as well. Both
+    /// mints succeed or fail together.
+    ///
     /// Emit `FtMint` event.
     #[payable]
-    pub fn deposit(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+    pub fn deposit(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        fee: Option<aurora_sdk::BridgeFee>,
+    ) {
         // Only the factory can deposit tokens
         self.assert_factory();
 
         // Mint exact amount of tokens for the receiver
         self.token.internal_deposit(&receiver_id, amount.into());
+        self.total_bridged_in += amount.0;
+
+        if let Some(fee) = fee.as_ref() {
+            self.token
+                .internal_deposit(&fee.collector_id, fee.amount.into());
+            self.total_bridged_in += fee.amount.0;
+        }
+        self.assert_supply_invariant();
 
         // Emit minting event
         FtMint {
@@ -127,6 +319,35 @@ impl Contract {
             memo: memo.as_deref(),
         }
         .emit();
+        if let Some(fee) = fee.as_ref() {
+            FtMint {
+                owner_id: &fee.collector_id,
+                amount: &fee.amount,
+                memo: Some("Bridge fee"),
+            }
+            .emit();
+        }
+    }
+
+    /// Mint back tokens that were burnt with `withdraw` but not unlocked on Aurora,
+    /// for example when a held withdrawal is cancelled. Unlike [`Self::deposit`] the
+    /// amount is not counted as bridged in. This method MUST be executed only if the
+    /// predecessor account id is the factory.
+    ///
+    /// Emit `FtMint` event.
+    pub fn remint(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        self.assert_factory();
+
+        self.token.internal_deposit(&receiver_id, amount.into());
+        self.total_reminted += amount.0;
+        self.assert_supply_invariant();
+
+        FtMint {
+            owner_id: &receiver_id,
+            amount: &amount,
+            memo: memo.as_deref(),
+        }
+        .emit();
     }
 
     /// Similar to `ft_transfer_call`. Allows the user to transfer from
@@ -156,6 +377,8 @@ impl Contract {
 
         // Mint tokens for the factory
         self.token.internal_deposit(&self.factory, amount.into());
+        self.total_bridged_in += amount.0;
+        self.assert_supply_invariant();
 
         // Emit minting event
         FtMint {
@@ -197,6 +420,8 @@ impl Contract {
             // Burn the tokens that were minted for the factory.
             let refund_amount = amount - used_amount;
             self.token.internal_withdraw(&self.factory, refund_amount);
+            self.total_refunded += refund_amount;
+            self.assert_supply_invariant();
 
             // Emit burning event
             let refund_amount = U128::from(refund_amount);
@@ -216,40 +441,114 @@ impl Contract {
 
     /// Burn tokens owned by the predecessor account id, and unlock the equivalent
     /// amount on Aurora for `receiver_id`. It decreases the total supply. Anyone
-    /// can call this method, including other contracts.
+    /// can call this method, including other contracts, unless the account is frozen.
+    ///
+    /// If a withdrawal fee is set, see [`Self::set_withdraw_fee`], the fee is first
+    /// transferred to the fee collector and only the rest is burnt and unlocked. It
+    /// fails if `amount` doesn't cover the fee.
     ///
-    /// Emit `FtBurn` event.
+    /// Each withdrawal is assigned a transfer id, unique for this token, which is
+    /// passed to the factory and the locker, so the burn can be traced to the unlock
+    /// on Aurora. The memo of the `FtBurn` event is prefixed with `withdraw:<id>`.
+    /// These ids are independent from the ids of deposits, which are the nonces of
+    /// the locker and appear in mint memos as `deposit:<id>`.
+    ///
+    /// Emit `FtBurn` event, and `FtTransfer` event if a fee is charged.
     pub fn withdraw(
         &mut self,
         receiver_id: aurora_sdk::Address,
         amount: U128,
         memo: Option<String>,
     ) -> Promise {
-        // Burn tokens from the factory
-        self.token
-            .internal_withdraw(&env::predecessor_account_id(), amount.into());
+        let owner_id = env::predecessor_account_id();
+        self.assert_not_frozen(&owner_id);
 
-        // Emit burning event
-        FtBurn {
-            owner_id: &env::predecessor_account_id(),
-            amount: &amount,
-            memo: memo.as_deref(),
-        }
-        .emit();
+        let amount = self.charge_withdraw_fee(&owner_id, amount);
+        self.internal_withdraw(&owner_id, receiver_id, amount, memo.as_deref())
+    }
 
-        ext_near_token_factory::ext(self.factory.clone())
-            .with_static_gas(GAS_FOR_ON_WITHDRAW)
-            .on_withdraw(receiver_id, amount)
+    /// Same as `withdraw`, but the tokens are unlocked for the address on Aurora
+    /// derived from the predecessor account id, i.e. the address the engine uses
+    /// for transactions submitted by that account.
+    ///
+    /// Emit `FtBurn` event, and `FtTransfer` event if a fee is charged.
+    pub fn withdraw_to_self(&mut self, amount: U128, memo: Option<String>) -> Promise {
+        let owner_id = env::predecessor_account_id();
+        self.assert_not_frozen(&owner_id);
+
+        let amount = self.charge_withdraw_fee(&owner_id, amount);
+        let receiver_id = aurora_sdk::near_account_to_evm_address(owner_id.as_bytes());
+        self.internal_withdraw(&owner_id, receiver_id, amount, memo.as_deref())
     }
 
     /// Upgrade the contract to a newer version. This method MUST be
     /// executed only if the predecessor account id is the factory.
+    ///
+    /// The state is migrated to the layout of the new version by calling
+    /// `migrate` in the same batch, so the new binary is never used with
+    /// state it can't decode.
     pub fn upgrade_contract(&mut self, binary: near_sdk::json_types::Base64VecU8) -> Promise {
         // Only the factory can upgrade the contract
         self.assert_factory();
 
-        // Deploy the new contract
-        Promise::new(env::current_account_id()).deploy_contract(binary.into())
+        // Deploy the new contract and migrate the state
+        Promise::new(env::current_account_id())
+            .deploy_contract(binary.into())
+            .function_call("migrate".to_string(), b"{}".to_vec(), 0, GAS_FOR_MIGRATE)
+    }
+
+    /// Migrate the stored state to the layout of this binary. It is called by
+    /// `upgrade_contract` right after deploying a new binary, and by the factory
+    /// after the upgrade, since the first binary doesn't call it from
+    /// `upgrade_contract`. Migrating state that is already up to date leaves it
+    /// unchanged. This method MUST be executed only if the predecessor account id
+    /// is the contract itself or the factory.
+    ///
+    /// The factory provides `bridged_token_info` to record the new binary version.
+    /// It is required to migrate the state of the first binary, see [`ContractV0`].
+    #[init(ignore_state)]
+    pub fn migrate(bridged_token_info: Option<aurora_sdk::BridgedTokenInfo>) -> Self {
+        let mut contract = match read_state_version() {
+            StateVersion::V0 => {
+                ContractV0::read().into_contract(bridged_token_info.clone().unwrap_or_else(|| {
+                    env::panic_str("Bridged token info is required to migrate the state")
+                }))
+            }
+            StateVersion::V1 => env::state_read::<Self>()
+                .unwrap_or_else(|| env::panic_str("Failed to read contract state")),
+        };
+
+        let predecessor_id = env::predecessor_account_id();
+        require!(
+            predecessor_id == env::current_account_id() || predecessor_id == contract.factory,
+            "Only the contract itself or the factory can call this method"
+        );
+        if let Some(info) = bridged_token_info {
+            contract.binary_version = info.binary_version;
+        }
+
+        write_state_version(CURRENT_STATE_VERSION);
+        contract
+    }
+
+    /// Version of the layout of the stored state.
+    pub fn state_version(&self) -> u8 {
+        read_state_version() as u8
+    }
+
+    /// Delete this account and send its balance to the factory, including the storage
+    /// deposits of accounts that are still registered. This method MUST be executed
+    /// only if the predecessor account id is the factory, and fails if any tokens are
+    /// still in circulation.
+    pub fn self_destruct(&mut self) -> Promise {
+        // Only the factory can delete the contract
+        self.assert_factory();
+        require!(
+            self.token.total_supply == 0,
+            "Can't delete token with non-zero total supply"
+        );
+
+        Promise::new(env::current_account_id()).delete_account(self.factory.clone())
     }
 
     /// Update the metadata for the token. ONLY accounts with
@@ -257,6 +556,16 @@ impl Contract {
     /// is expected that the factory has this role. This allows a trustless
     /// workflow where metadata can be updated by any user starting the call
     /// from the locker in Aurora.
+    ///
+    /// Decimals can only be set while they are not frozen, otherwise the value
+    /// provided must match the current one. They are frozen after the first update
+    /// that sets them, so deposits made before the first metadata sync don't block
+    /// it. Use [`Self::override_decimals`] to change them afterwards.
+    ///
+    /// The resulting metadata must be valid according to NEP-148, in particular
+    /// `reference` and `reference_hash` must be either both set or both unset.
+    ///
+    /// Emit `ft_metadata_update` event if any field changed.
     #[access_control_any(roles(AclRole::MetadataUpdater))]
     pub fn update_metadata(&mut self, metadata: aurora_sdk::UpdateFungibleTokenMetadata) {
         let aurora_sdk::UpdateFungibleTokenMetadata {
@@ -268,6 +577,16 @@ impl Contract {
             decimals,
         } = metadata;
 
+        if let Some(decimals) = decimals {
+            require!(
+                !self.decimals_frozen || decimals == self.metadata.decimals,
+                "Decimals are frozen and can't be updated"
+            );
+            self.decimals_frozen = true;
+        }
+
+        let old_metadata = self.metadata.clone();
+
         // Update only parts of the metadata that were specified.
         maybe_update_metadata!(self, name);
         maybe_update_metadata!(self, symbol);
@@ -275,17 +594,277 @@ impl Contract {
         maybe_update_optional_metadata!(self, reference);
         maybe_update_optional_metadata!(self, reference_hash);
         maybe_update_metadata!(self, decimals);
+
+        self.metadata.assert_valid();
+
+        self.emit_metadata_update(&old_metadata);
+    }
+
+    /// Change the decimals of the token even if they are already frozen. This will
+    /// rescale every balance as displayed by wallets, so it should only be used to
+    /// fix a wrong value. ONLY the access control super-admin can call this method.
+    ///
+    /// Emit `ft_decimals_override` and `ft_metadata_update` events.
+    pub fn override_decimals(&mut self, decimals: u8) {
+        self.assert_super_admin();
+        let updated_by = env::predecessor_account_id();
+
+        let old_metadata = self.metadata.clone();
+        self.metadata.decimals = decimals;
+        self.decimals_frozen = true;
+
+        FtDecimalsOverride {
+            old_decimals: old_metadata.decimals,
+            new_decimals: decimals,
+            updated_by: &updated_by,
+        }
+        .emit();
+
+        self.emit_metadata_update(&old_metadata);
+    }
+
+    /// Set the Aurora address that receives the balance of accounts that are force
+    /// unregistered, see `storage_unregister`. ONLY the access control super-admin
+    /// can call this method.
+    pub fn set_unregister_refund_address(&mut self, address: Option<aurora_sdk::Address>) {
+        self.assert_super_admin();
+        self.unregister_refund_address = address;
+    }
+
+    /// Set the fee charged on withdrawals, or stop charging it if `None`. The fee
+    /// collector MUST be registered. This method MUST be executed only if the
+    /// predecessor account id is the factory.
+    pub fn set_withdraw_fee(&mut self, fee: Option<aurora_sdk::WithdrawFee>) {
+        self.assert_factory();
+        self.withdraw_fee = fee;
+    }
+
+    /// Returns the fee charged on withdrawals, if any.
+    pub fn withdraw_fee(&self) -> Option<aurora_sdk::WithdrawFee> {
+        self.withdraw_fee.clone()
+    }
+
+    /// Returns the Aurora address that receives the balance of force unregistered
+    /// accounts, if any.
+    pub fn unregister_refund_address(&self) -> Option<aurora_sdk::Address> {
+        self.unregister_refund_address.clone()
+    }
+
+    /// Returns which ERC-20 token this contract represents and which contracts are
+    /// involved in bridging it.
+    pub fn bridged_token_info(&self) -> aurora_sdk::BridgedTokenInfo {
+        aurora_sdk::BridgedTokenInfo {
+            erc20_address: self.erc20_address.clone(),
+            aurora: self.aurora.clone(),
+            locker: self.locker.clone(),
+            factory: self.factory.clone(),
+            binary_version: self.binary_version,
+        }
+    }
+
+    /// Returns lifetime counters of bridged tokens. The total supply always equals
+    /// `total_bridged_in - total_bridged_out + total_reminted - total_refunded -
+    /// total_burned`.
+    pub fn bridge_accounting(&self) -> BridgeAccounting {
+        BridgeAccounting {
+            total_supply: self.token.total_supply.into(),
+            total_bridged_in: self.total_bridged_in.into(),
+            total_bridged_out: self.total_bridged_out.into(),
+            total_reminted: self.total_reminted.into(),
+            total_refunded: self.total_refunded.into(),
+            total_burned: self.total_burned.into(),
+        }
+    }
+
+    /// Returns whether decimals are frozen, see [`Self::update_metadata`].
+    pub fn decimals_frozen(&self) -> bool {
+        self.decimals_frozen
+    }
+
+    /// Freeze `account_id`, so it can't call `ft_transfer`, `ft_transfer_call` or
+    /// `withdraw`. Frozen accounts can still receive tokens, in particular refunds.
+    /// ONLY accounts with [`AclRole::Compliance`] role can call this method.
+    ///
+    /// Return `false` if the account was already frozen. Emit `account_frozen`
+    /// event otherwise.
+    #[access_control_any(roles(AclRole::Compliance))]
+    pub fn freeze_account(&mut self, account_id: AccountId) -> bool {
+        if !self.frozen_accounts.insert(&account_id) {
+            return false;
+        }
+
+        AccountFrozen {
+            account_id: &account_id,
+            frozen_by: &env::predecessor_account_id(),
+        }
+        .emit();
+
+        true
+    }
+
+    /// Unfreeze `account_id`. ONLY accounts with [`AclRole::Compliance`] role can
+    /// call this method.
+    ///
+    /// Return `false` if the account was not frozen. Emit `account_unfrozen` event
+    /// otherwise.
+    #[access_control_any(roles(AclRole::Compliance))]
+    pub fn unfreeze_account(&mut self, account_id: AccountId) -> bool {
+        if !self.frozen_accounts.remove(&account_id) {
+            return false;
+        }
+
+        AccountUnfrozen {
+            account_id: &account_id,
+            unfrozen_by: &env::predecessor_account_id(),
+        }
+        .emit();
+
+        true
+    }
+
+    /// Returns whether `account_id` is frozen.
+    pub fn is_account_frozen(&self, account_id: AccountId) -> bool {
+        self.frozen_accounts.contains(&account_id)
+    }
+
+    /// Returns up to `limit` frozen accounts after skipping the first `skip` ones.
+    pub fn get_frozen_accounts(&self, skip: u64, limit: u64) -> Vec<AccountId> {
+        self.frozen_accounts
+            .iter()
+            .skip(skip as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    /// Returns the number of frozen accounts.
+    pub fn get_frozen_accounts_count(&self) -> u64 {
+        self.frozen_accounts.len()
     }
 
     /// Triggers call in ERC20 Locker contract on Aurora to update the metadata of
     /// this contract. This method is public and can be called by any user. The effect
     /// of this method is that the fields "name", "symbol" and "decimals" are updated.
     /// Other fields remain unchanged.
+    ///
+    /// The new values arrive through [`Self::update_metadata`], which emits the
+    /// `ft_metadata_update` event.
     pub fn pull_metadata(&mut self) -> Promise {
         todo!()
     }
 }
 
+#[near_bindgen]
+impl FungibleTokenCore for Contract {
+    #[payable]
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        self.assert_not_frozen(&env::predecessor_account_id());
+        self.token.ft_transfer(receiver_id, amount, memo)
+    }
+
+    #[payable]
+    fn ft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        self.assert_not_frozen(&env::predecessor_account_id());
+        self.token.ft_transfer_call(receiver_id, amount, memo, msg)
+    }
+
+    fn ft_total_supply(&self) -> U128 {
+        self.token.ft_total_supply()
+    }
+
+    fn ft_balance_of(&self, account_id: AccountId) -> U128 {
+        self.token.ft_balance_of(account_id)
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenResolver for Contract {
+    /// Refunds are not restricted for frozen accounts, so unused tokens always
+    /// return to the sender.
+    #[private]
+    fn ft_resolve_transfer(
+        &mut self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> U128 {
+        let (used_amount, burned_amount) =
+            self.token
+                .internal_ft_resolve_transfer(&sender_id, receiver_id, amount);
+        if burned_amount > 0 {
+            self.total_burned += burned_amount;
+            self.assert_supply_invariant();
+        }
+        used_amount.into()
+    }
+}
+
+#[near_bindgen]
+impl StorageManagement for Contract {
+    #[payable]
+    fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        self.token.storage_deposit(account_id, registration_only)
+    }
+
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        self.token.storage_withdraw(amount)
+    }
+
+    /// Unregister the predecessor account. Unlike the default implementation, the
+    /// remaining balance of a force unregistered account is not simply burnt, since
+    /// the equivalent ERC-20 tokens would stay locked on Aurora forever. Instead it
+    /// is withdrawn to [`Contract::unregister_refund_address`] through the regular
+    /// withdraw flow. If no refund address is set, unregistering an account with
+    /// positive balance fails, even if `force` is set. Frozen accounts can only
+    /// unregister with zero balance.
+    ///
+    /// Emit `FtBurn` event if the account had positive balance.
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let balance = self.token.accounts.get(&account_id).unwrap_or(0);
+
+        if balance > 0 && force.unwrap_or(false) {
+            self.assert_not_frozen(&account_id);
+            let receiver_id = match self.unregister_refund_address.clone() {
+                Some(address) => address,
+                None => env::panic_str(
+                    "Can't unregister the account with positive balance without refund address",
+                ),
+            };
+
+            // The promise is scheduled even if it is not returned.
+            self.internal_withdraw(
+                &account_id,
+                receiver_id,
+                balance.into(),
+                Some("Refund balance from storage_unregister"),
+            );
+        }
+
+        self.token.internal_storage_unregister(force).is_some()
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        self.token.storage_balance_bounds()
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.token.storage_balance_of(account_id)
+    }
+}
+
 #[near_bindgen]
 impl FungibleTokenMetadataProvider for Contract {
     /// Returns the metadata for the token.
@@ -295,6 +874,97 @@ impl FungibleTokenMetadataProvider for Contract {
 }
 
 impl Contract {
+    fn emit_metadata_update(&self, old_metadata: &FungibleTokenMetadata) {
+        let updated_by = env::predecessor_account_id();
+        if let Some(event) = FtMetadataUpdate::from_diff(&updated_by, old_metadata, &self.metadata)
+        {
+            event.emit();
+        }
+    }
+
+    /// Transfer the withdrawal fee for `amount` from `owner_id` to the fee collector.
+    /// Returns the amount left to withdraw. The fee collector withdraws without fee.
+    fn charge_withdraw_fee(&mut self, owner_id: &AccountId, amount: U128) -> U128 {
+        let (collector_id, fee) = match self.withdraw_fee.as_ref() {
+            Some(fee) if &fee.collector_id != owner_id => {
+                (fee.collector_id.clone(), fee.schedule.fee(amount.0))
+            }
+            _ => return amount,
+        };
+        require!(fee < amount.0, "Amount doesn't cover the withdrawal fee");
+
+        if fee > 0 {
+            self.token.internal_transfer(
+                owner_id,
+                &collector_id,
+                fee,
+                Some("Bridge fee".to_string()),
+            );
+        }
+        (amount.0 - fee).into()
+    }
+
+    /// Burn `amount` tokens from `owner_id` and ask the factory to unlock the same
+    /// amount on Aurora for `receiver_id`, with the next transfer id.
+    fn internal_withdraw(
+        &mut self,
+        owner_id: &AccountId,
+        receiver_id: aurora_sdk::Address,
+        amount: U128,
+        memo: Option<&str>,
+    ) -> Promise {
+        // Burn tokens from the owner
+        self.token.internal_withdraw(owner_id, amount.into());
+        self.total_bridged_out += amount.0;
+        self.assert_supply_invariant();
+
+        let transfer_id = self.next_transfer_id;
+        self.next_transfer_id += 1;
+
+        // Emit burning event
+        let memo = match memo {
+            Some(memo) => format!("withdraw:{} {}", transfer_id, memo),
+            None => format!("withdraw:{}", transfer_id),
+        };
+        FtBurn {
+            owner_id,
+            amount: &amount,
+            memo: Some(&memo),
+        }
+        .emit();
+
+        ext_near_token_factory::ext(self.factory.clone())
+            .with_static_gas(GAS_FOR_ON_WITHDRAW)
+            .on_withdraw(owner_id.clone(), receiver_id, amount, transfer_id)
+    }
+
+    /// Every token in circulation must have been bridged from Aurora and not yet
+    /// returned, otherwise the locker balance and the total supply diverge.
+    fn assert_supply_invariant(&self) {
+        require!(
+            self.token.total_supply
+                == self.total_bridged_in + self.total_reminted
+                    - self.total_bridged_out
+                    - self.total_refunded
+                    - self.total_burned,
+            "Total supply doesn't match bridged amounts"
+        );
+    }
+
+    fn assert_super_admin(&self) {
+        require!(
+            self.acl_is_super_admin(env::predecessor_account_id()),
+            "Only super-admin can call this method"
+        );
+    }
+
+    fn assert_not_frozen(&self, account_id: &AccountId) {
+        require!(
+            !self.frozen_accounts.contains(account_id),
+            "Account is frozen"
+        );
+    }
+
     fn assert_factory(&self) {
         require!(
             env::predecessor_account_id() == self.factory,
@@ -303,6 +973,24 @@ impl Contract {
     }
 }
 
+/// Read the version of the stored state. Tokens deployed before the version was
+/// stored use the first layout.
+fn read_state_version() -> StateVersion {
+    env::storage_read(&StorageKeys::StateVersion.try_to_vec().unwrap())
+        .map(|version| {
+            StateVersion::try_from_slice(&version)
+                .unwrap_or_else(|_| env::panic_str("Failed to read state version"))
+        })
+        .unwrap_or(StateVersion::V0)
+}
+
+fn write_state_version(version: StateVersion) {
+    env::storage_write(
+        &StorageKeys::StateVersion.try_to_vec().unwrap(),
+        &version.try_to_vec().unwrap(),
+    );
+}
+
 fn unwrap_promise<T>(promise_or_value: PromiseOrValue<T>) -> near_sdk::Promise {
     match promise_or_value {
         PromiseOrValue::Promise(promise) => promise,
@@ -310,9 +998,6 @@ fn unwrap_promise<T>(promise_or_value: PromiseOrValue<T>) -> near_sdk::Promise {
     }
 }
 
-near_contract_standards::impl_fungible_token_core!(Contract, token);
-near_contract_standards::impl_fungible_token_storage!(Contract, token);
-
 fn default_metadata() -> FungibleTokenMetadata {
     FungibleTokenMetadata {
         spec: "ft-1.0.0".to_string(),
@@ -0,0 +1,263 @@
+use crate::events::TokenBinaryActivated;
+use crate::time::{self, NANOS_PER_SECOND};
+use crate::{wasm, AclRole, Contract, ContractExt};
+use near_plugins::access_control_any;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, require, AccountId};
+
+const ERR_BINARY_VERSION_NOT_FOUND: &str =
+    "ERR_BINARY_VERSION_NOT_FOUND: Token binary version doesn't exist.";
+const ERR_BINARY_IN_USE: &str = "ERR_BINARY_IN_USE: Active or staged token binary can't be pruned.";
+const ERR_NO_STAGED_BINARY: &str = "ERR_NO_STAGED_BINARY: No token binary is staged.";
+const ERR_STAGED_BINARY_LOCKED: &str =
+    "ERR_STAGED_BINARY_LOCKED: Staged token binary can't be activated yet.";
+const ERR_INVALID_WASM: &str = "ERR_INVALID_WASM: Token binary is not a valid WASM module.";
+const ERR_MISSING_EXPORTS: &str = "ERR_MISSING_EXPORTS: Token binary is missing required exports";
+const ERR_NOT_LEGACY_BINARY: &str =
+    "ERR_NOT_LEGACY_BINARY: Token binary exports all required methods, use set_token_binary.";
+const ERR_NO_BINARY_UPLOAD: &str = "ERR_NO_BINARY_UPLOAD: No token binary upload in progress.";
+const ERR_BINARY_HASH_MISMATCH: &str =
+    "ERR_BINARY_HASH_MISMATCH: Hash of the uploaded binary doesn't match the expected hash.";
+
+/// Record of a token binary uploaded to the factory.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenBinaryInfo {
+    pub version: u32,
+    /// Hex encoded sha256 hash of the binary.
+    pub hash: String,
+    pub uploaded_by: AccountId,
+    /// Block timestamp in nanoseconds when the binary was uploaded.
+    pub uploaded_at: u64,
+}
+
+/// Token binary waiting to be activated.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StagedTokenBinary {
+    pub version: u32,
+    /// Block timestamp in nanoseconds after which the binary can be activated.
+    pub activate_at: u64,
+}
+
+/// Token binary being uploaded in chunks.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BinaryUpload {
+    pub started_by: AccountId,
+    /// Block timestamp in nanoseconds when the upload started.
+    pub started_at: u64,
+    /// Number of chunks uploaded so far.
+    pub chunks: u64,
+    /// Size in bytes of the chunks uploaded so far.
+    pub size: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Upload a new token binary without activating it. It can be activated with
+    /// `activate_token_binary` once `delay_sec` seconds passed. Replaces the binary
+    /// that was staged before, if any. Returns the version of the new binary. ONLY
+    /// accounts with [`AclRole::Admin`] role can call this method.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn stage_token_binary(&mut self, binary: Base64VecU8, delay_sec: Option<u64>) -> u32 {
+        let version = self.register_token_binary(binary.into());
+        self.stage_registered_token_binary(version, delay_sec);
+        version
+    }
+
+    /// Activate the staged token binary, so it is used for new tokens. All deployed
+    /// tokens SHOULD be upgraded after calling this method. This is a public method
+    /// with no access control, since only a binary staged by an admin can be
+    /// activated.
+    ///
+    /// Emit `token_binary_activated` event.
+    pub fn activate_token_binary(&mut self) -> u32 {
+        let staged = self
+            .staged_token_binary
+            .take()
+            .unwrap_or_else(|| env::panic_str(ERR_NO_STAGED_BINARY));
+        require!(
+            env::block_timestamp() >= staged.activate_at,
+            ERR_STAGED_BINARY_LOCKED
+        );
+
+        self.activate_token_binary_version(staged.version);
+        staged.version
+    }
+
+    /// Activate a previously uploaded token binary `version`, e.g. to revert a faulty
+    /// binary. The staged binary, if any, is discarded, so it can't be activated
+    /// afterwards. ONLY accounts with [`AclRole::Admin`] role can call this method.
+    ///
+    /// Emit `token_binary_activated` event.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn rollback_token_binary(&mut self, version: u32) {
+        require!(
+            self.token_binaries.contains_key(&version),
+            ERR_BINARY_VERSION_NOT_FOUND
+        );
+        self.staged_token_binary = None;
+        self.activate_token_binary_version(version);
+    }
+
+    /// Delete the stored token binary `version` to release its storage. Its record in
+    /// the history is kept, but it can't be activated anymore. The active and staged
+    /// binaries can't be pruned. ONLY accounts with [`AclRole::Admin`] role can call
+    /// this method.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn prune_token_binary(&mut self, version: u32) {
+        let staged_version = self
+            .staged_token_binary
+            .as_ref()
+            .map(|staged| staged.version);
+        require!(
+            version != self.token_binary_version && Some(version) != staged_version,
+            ERR_BINARY_IN_USE
+        );
+        require!(
+            self.token_binaries.remove(&version).is_some(),
+            ERR_BINARY_VERSION_NOT_FOUND
+        );
+    }
+
+    /// Start uploading a token binary in chunks, for binaries that don't fit in the
+    /// arguments of a single transaction. Discards any upload in progress. ONLY
+    /// accounts with [`AclRole::Admin`] role can call this method.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn begin_binary_upload(&mut self) {
+        self.binary_upload_chunks.clear();
+        self.binary_upload = Some(BinaryUpload {
+            started_by: env::predecessor_account_id(),
+            started_at: env::block_timestamp(),
+            chunks: 0,
+            size: 0,
+        });
+    }
+
+    /// Append `chunk` to the token binary being uploaded. ONLY accounts with
+    /// [`AclRole::Admin`] role can call this method.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn append_binary_chunk(&mut self, chunk: Base64VecU8) {
+        let upload = self
+            .binary_upload
+            .as_mut()
+            .unwrap_or_else(|| env::panic_str(ERR_NO_BINARY_UPLOAD));
+        upload.chunks += 1;
+        upload.size += chunk.0.len() as u64;
+        self.binary_upload_chunks.push(&chunk.into());
+    }
+
+    /// Assemble the uploaded chunks and stage the resulting token binary, see
+    /// `stage_token_binary`. Fails if the hex encoded sha256 hash of the binary is not
+    /// `expected_hash`. Returns the version of the new binary. ONLY accounts with
+    /// [`AclRole::Admin`] role can call this method.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn finalize_binary_upload(&mut self, expected_hash: String, delay_sec: Option<u64>) -> u32 {
+        require!(self.binary_upload.is_some(), ERR_NO_BINARY_UPLOAD);
+
+        let binary: Vec<u8> = self.binary_upload_chunks.iter().flatten().collect();
+        require!(
+            hex::encode(env::sha256(&binary)) == expected_hash.to_lowercase(),
+            ERR_BINARY_HASH_MISMATCH
+        );
+
+        self.binary_upload = None;
+        self.binary_upload_chunks.clear();
+
+        let version = self.register_token_binary(binary);
+        self.stage_registered_token_binary(version, delay_sec);
+        version
+    }
+
+    /// Returns the token binary upload in progress, if any.
+    pub fn get_binary_upload(&self) -> Option<BinaryUpload> {
+        self.binary_upload.clone()
+    }
+
+    /// Returns the methods required for token binaries that are not exported by
+    /// `binary`. Binaries missing any of them are rejected by the factory.
+    pub fn validate_token_binary(&self, binary: Base64VecU8) -> Vec<String> {
+        wasm::missing_token_exports(&binary.0).unwrap_or_else(|| env::panic_str(ERR_INVALID_WASM))
+    }
+
+    /// Returns the version of the token binary used for new tokens.
+    pub fn get_token_binary_version(&self) -> u32 {
+        self.token_binary_version
+    }
+
+    /// Returns the token binary waiting to be activated, if any.
+    pub fn get_staged_token_binary(&self) -> Option<StagedTokenBinary> {
+        self.staged_token_binary.clone()
+    }
+
+    /// Returns up to `limit` uploaded token binaries ordered by version, after
+    /// skipping the first `skip` ones.
+    pub fn get_token_binary_history(&self, skip: u64, limit: u64) -> Vec<TokenBinaryInfo> {
+        self.token_binary_history
+            .iter()
+            .skip(skip as usize)
+            .take(limit as usize)
+            .collect()
+    }
+}
+
+impl Contract {
+    /// Store `binary` with the next version and record it in the history. Returns the
+    /// version of the binary. Fails if it doesn't export all methods required for
+    /// tokens, listing the missing ones.
+    pub(crate) fn register_token_binary(&mut self, binary: Vec<u8>) -> u32 {
+        let missing_exports = wasm::missing_token_exports(&binary)
+            .unwrap_or_else(|| env::panic_str(ERR_INVALID_WASM));
+        if !missing_exports.is_empty() {
+            env::panic_str(&format!(
+                "{}: {}.",
+                ERR_MISSING_EXPORTS,
+                missing_exports.join(", ")
+            ));
+        }
+        self.record_token_binary(binary)
+    }
+
+    /// Same as [`Self::register_token_binary`], but only accepts binaries that miss
+    /// some of the required exports, i.e. binaries that predate them.
+    pub(crate) fn register_legacy_token_binary(&mut self, binary: Vec<u8>) -> u32 {
+        let missing_exports = wasm::missing_token_exports(&binary)
+            .unwrap_or_else(|| env::panic_str(ERR_INVALID_WASM));
+        require!(!missing_exports.is_empty(), ERR_NOT_LEGACY_BINARY);
+        self.record_token_binary(binary)
+    }
+
+    fn record_token_binary(&mut self, binary: Vec<u8>) -> u32 {
+        let version = self.token_binary_history.len() as u32 + 1;
+        self.token_binary_history.push(&TokenBinaryInfo {
+            version,
+            hash: hex::encode(env::sha256(&binary)),
+            uploaded_by: env::predecessor_account_id(),
+            uploaded_at: env::block_timestamp(),
+        });
+        self.token_binaries.insert(&version, &binary);
+        version
+    }
+
+    pub(crate) fn stage_registered_token_binary(&mut self, version: u32, delay_sec: Option<u64>) {
+        let delay_sec = delay_sec.unwrap_or(0);
+        time::assert_valid_delay(delay_sec);
+        self.staged_token_binary = Some(StagedTokenBinary {
+            version,
+            activate_at: env::block_timestamp() + delay_sec * NANOS_PER_SECOND,
+        });
+    }
+
+    pub(crate) fn activate_token_binary_version(&mut self, version: u32) {
+        TokenBinaryActivated {
+            version,
+            previous_version: self.token_binary_version,
+            activated_by: &env::predecessor_account_id(),
+        }
+        .emit();
+        self.token_binary_version = version;
+    }
+}
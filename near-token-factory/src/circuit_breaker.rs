@@ -0,0 +1,343 @@
+use crate::events::CircuitBreakerTripped;
+use crate::pause::Direction;
+use crate::time::{self, NANOS_PER_SECOND};
+use crate::{account_id_from_token_address, AclRole, Contract, ContractExt};
+use near_plugins::access_control_any;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId, Balance};
+use near_token_common as aurora_sdk;
+
+/// Number of sub-buckets the sliding window is split into. The sliding window is
+/// advanced one sub-bucket at a time.
+const SUB_BUCKETS: usize = 8;
+/// Number of windows over which the trailing average is computed.
+const AVERAGE_WINDOWS: u32 = 8;
+/// Maximum number of elapsed sub-buckets folded into the average at once. Older
+/// sub-buckets have a negligible weight.
+const MAX_CLOSED_BUCKETS: u64 = 64 * SUB_BUCKETS as u64;
+
+pub(crate) const ERR_CIRCUIT_BREAKER_TRIPPED: &str =
+    "ERR_CIRCUIT_BREAKER_TRIPPED: Direction was paused by its circuit breaker, an admin must reset it.";
+
+/// Conditions under which bridging a token in one direction is paused automatically.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CircuitBreakerConfig {
+    pub window_sec: u64,
+    /// Trip when the volume of the sliding window exceeds this multiple of the
+    /// trailing average volume per window.
+    pub max_multiple: Option<u32>,
+    /// Trip when the volume of the sliding window exceeds this amount.
+    pub absolute_cap: Option<U128>,
+}
+
+/// Volume bridged within a sliding window, together with the trailing average of
+/// the volume per window. The sliding window is a ring of [`SUB_BUCKETS`] sub-buckets,
+/// the last of which is the current one.
+#[derive(BorshDeserialize, BorshSerialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct VolumeHistory {
+    /// Start of the current sub-bucket, in nanoseconds. Zero before the first transfer.
+    pub bucket_start: u64,
+    /// Volume bridged in each sub-bucket of the sliding window.
+    pub buckets: [Balance; SUB_BUCKETS],
+    /// Index of the current sub-bucket in `buckets`.
+    pub head: u8,
+    /// Trailing average of the volume of sub-buckets that left the sliding window.
+    pub bucket_average: Balance,
+    /// Number of sub-buckets included in the average, up to `AVERAGE_WINDOWS` windows.
+    pub averaged_buckets: u32,
+    /// Whether the circuit breaker tripped and wasn't reset by an admin yet.
+    pub tripped: bool,
+}
+
+impl VolumeHistory {
+    /// Move the sliding window forward so that `now` is inside the current
+    /// sub-bucket, folding the volume of sub-buckets that left it into the trailing
+    /// average.
+    pub fn advance(&mut self, window: u64, now: u64) {
+        let bucket = window / SUB_BUCKETS as u64;
+        if self.bucket_start == 0 {
+            self.bucket_start = now - now % bucket;
+            return;
+        }
+        if now < self.bucket_start.saturating_add(bucket) {
+            return;
+        }
+
+        let elapsed = (now - self.bucket_start) / bucket;
+        for _ in 0..elapsed.min(MAX_CLOSED_BUCKETS) {
+            self.head = ((usize::from(self.head) + 1) % SUB_BUCKETS) as u8;
+            let head = usize::from(self.head);
+            self.close_bucket(self.buckets[head]);
+            self.buckets[head] = 0;
+        }
+        self.bucket_start += elapsed * bucket;
+    }
+
+    fn close_bucket(&mut self, volume: Balance) {
+        let max_buckets = AVERAGE_WINDOWS * SUB_BUCKETS as u32;
+        if self.averaged_buckets < max_buckets {
+            // Plain mean until enough sub-buckets are available, computed from the
+            // difference to the previous mean so it can't overflow.
+            let buckets = u128::from(self.averaged_buckets) + 1;
+            self.bucket_average = if volume >= self.bucket_average {
+                self.bucket_average + (volume - self.bucket_average) / buckets
+            } else {
+                self.bucket_average - (self.bucket_average - volume) / buckets
+            };
+            self.averaged_buckets += 1;
+        } else {
+            // Equivalent to `average * (buckets - 1) / buckets + volume / buckets`
+            // without overflowing.
+            let buckets = u128::from(max_buckets);
+            let average = self.bucket_average / buckets * (buckets - 1)
+                + self.bucket_average % buckets * (buckets - 1) / buckets;
+            self.bucket_average = average.saturating_add(volume / buckets);
+        }
+    }
+
+    /// Volume bridged within the sliding window. The window MUST be advanced before
+    /// calling this method.
+    pub fn volume(&self) -> Balance {
+        self.buckets
+            .iter()
+            .fold(0, |total, volume| total.saturating_add(*volume))
+    }
+
+    /// Trailing average of the volume per window.
+    pub fn average(&self) -> Balance {
+        self.bucket_average.saturating_mul(SUB_BUCKETS as u128)
+    }
+
+    /// Record `amount` in the current sub-bucket. Returns whether the volume of the
+    /// sliding window now violates `config`.
+    pub fn record(&mut self, config: &CircuitBreakerConfig, now: u64, amount: Balance) -> bool {
+        self.advance(config.window_sec * NANOS_PER_SECOND, now);
+        let head = usize::from(self.head);
+        self.buckets[head] = self.buckets[head].saturating_add(amount);
+
+        let volume = self.volume();
+        let average = self.average();
+        let over_cap = config.absolute_cap.map_or(false, |cap| volume > cap.0);
+        let over_average = match config.max_multiple {
+            Some(multiple) if average > 0 => average
+                .checked_mul(u128::from(multiple))
+                .map_or(false, |max| volume > max),
+            _ => false,
+        };
+        over_cap || over_average
+    }
+}
+
+/// Configuration and current state of a circuit breaker, see
+/// [`Contract::get_circuit_breaker`].
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CircuitBreakerStatus {
+    pub config: CircuitBreakerConfig,
+    pub current_volume: U128,
+    pub trailing_average: U128,
+    pub tripped: bool,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Set the circuit breaker for bridging `token` in `direction`, or remove it if
+    /// `config` is `None`. ONLY accounts with [`AclRole::Admin`] role can call this
+    /// method.
+    ///
+    /// When the circuit breaker trips, the direction is paused until an admin resets
+    /// it with `reset_circuit_breaker`. Pause managers can't unpause it. The transfer
+    /// that trips the breaker can't be rejected, since that would revert the pause as
+    /// well. A deposit is still minted and a withdrawal is held.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn set_circuit_breaker(
+        &mut self,
+        token: aurora_sdk::Address,
+        direction: Direction,
+        config: Option<CircuitBreakerConfig>,
+    ) {
+        let key = (account_id_from_token_address(token), direction);
+        match config {
+            Some(config) => {
+                time::assert_valid_window(config.window_sec);
+                self.circuit_breakers.insert(&key, &config);
+            }
+            None => {
+                self.circuit_breakers.remove(&key);
+                self.volume_history.remove(&key);
+            }
+        }
+    }
+
+    /// Returns the circuit breaker for bridging `token` in `direction`, if any.
+    pub fn get_circuit_breaker(
+        &self,
+        token: aurora_sdk::Address,
+        direction: Direction,
+    ) -> Option<CircuitBreakerStatus> {
+        let key = (account_id_from_token_address(token), direction);
+        let config = self.circuit_breakers.get(&key)?;
+
+        let mut history = self.volume_history.get(&key).unwrap_or_default();
+        history.advance(config.window_sec * NANOS_PER_SECOND, env::block_timestamp());
+
+        Some(CircuitBreakerStatus {
+            config,
+            current_volume: history.volume().into(),
+            trailing_average: history.average().into(),
+            tripped: history.tripped,
+        })
+    }
+
+    /// Reset the tripped circuit breaker for bridging `token` in `direction` and
+    /// resume bridging in that direction. ONLY accounts with [`AclRole::Admin`] role
+    /// can call this method.
+    ///
+    /// Emit `token_paused` event.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn reset_circuit_breaker(&mut self, token: aurora_sdk::Address, direction: Direction) {
+        let key = (account_id_from_token_address(token), direction);
+        if let Some(mut history) = self.volume_history.get(&key) {
+            history.tripped = false;
+            self.volume_history.insert(&key, &history);
+        }
+        self.set_paused(&key.0, Some(direction), false, "circuit_breaker_reset");
+    }
+}
+
+impl Contract {
+    /// Record `amount` bridged for `token_account_id` in `direction`, and pause the
+    /// direction if the circuit breaker trips.
+    ///
+    /// Emit `circuit_breaker_tripped` and `token_paused` events if it trips.
+    pub(crate) fn record_volume(
+        &mut self,
+        token_account_id: &AccountId,
+        direction: Direction,
+        amount: Balance,
+    ) {
+        let key = (token_account_id.clone(), direction);
+        let config = match self.circuit_breakers.get(&key) {
+            Some(config) => config,
+            None => return,
+        };
+
+        let mut history = self.volume_history.get(&key).unwrap_or_default();
+        let tripped = history.record(&config, env::block_timestamp(), amount)
+            && !self.is_paused(token_account_id, direction);
+        history.tripped |= tripped;
+        self.volume_history.insert(&key, &history);
+
+        if tripped {
+            CircuitBreakerTripped {
+                token_id: token_account_id,
+                direction,
+                current_volume: history.volume().into(),
+                trailing_average: history.average().into(),
+            }
+            .emit();
+            self.set_paused(token_account_id, Some(direction), true, "circuit_breaker");
+        }
+    }
+
+    /// Returns whether the circuit breaker for bridging `token_account_id` in
+    /// `direction` tripped and wasn't reset yet.
+    pub(crate) fn circuit_breaker_tripped(
+        &self,
+        token_account_id: &AccountId,
+        direction: Direction,
+    ) -> bool {
+        self.volume_history
+            .get(&(token_account_id.clone(), direction))
+            .map_or(false, |history| history.tripped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CircuitBreakerConfig, VolumeHistory, NANOS_PER_SECOND};
+
+    const WINDOW_SEC: u64 = 100;
+    const WINDOW: u64 = WINDOW_SEC * NANOS_PER_SECOND;
+
+    fn config(max_multiple: Option<u32>, absolute_cap: Option<u128>) -> CircuitBreakerConfig {
+        CircuitBreakerConfig {
+            window_sec: WINDOW_SEC,
+            max_multiple,
+            absolute_cap: absolute_cap.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn test_absolute_cap() {
+        let config = config(None, Some(100));
+        let mut history = VolumeHistory::default();
+        assert!(!history.record(&config, 10 * WINDOW, 60));
+        assert!(!history.record(&config, 10 * WINDOW + 1, 40));
+        assert!(history.record(&config, 10 * WINDOW + 2, 1));
+
+        // Volume leaves the sliding window one window later.
+        assert!(!history.record(&config, 11 * WINDOW, 100));
+    }
+
+    #[test]
+    fn test_sliding_window() {
+        let config = config(None, Some(100));
+        let mut history = VolumeHistory::default();
+        assert!(!history.record(&config, 10 * WINDOW, 0));
+
+        // Transfers on both sides of a window boundary add up.
+        assert!(!history.record(&config, 11 * WINDOW - 1, 60));
+        assert!(history.record(&config, 11 * WINDOW + 1, 60));
+
+        // Until the sub-bucket holding the first one leaves the sliding window.
+        history.advance(WINDOW, 12 * WINDOW - WINDOW / 8 - 1);
+        assert_eq!(history.volume(), 120);
+        history.advance(WINDOW, 12 * WINDOW - WINDOW / 8);
+        assert_eq!(history.volume(), 60);
+    }
+
+    #[test]
+    fn test_multiple_of_trailing_average() {
+        let config = config(Some(3), None);
+        let mut history = VolumeHistory::default();
+
+        // Without history only the absolute cap applies.
+        assert!(!history.record(&config, 10 * WINDOW, 1_000));
+        for window in 11..30 {
+            assert!(!history.record(&config, window * WINDOW, 1_000));
+        }
+        assert!((900..=1_000).contains(&history.average()));
+
+        assert!(!history.record(&config, 30 * WINDOW, 2_500));
+        assert!(history.record(&config, 30 * WINDOW + 1, 500));
+    }
+
+    #[test]
+    fn test_idle_windows_lower_average() {
+        let config = config(Some(3), None);
+        let mut history = VolumeHistory::default();
+        for window in 10..20 {
+            history.record(&config, window * WINDOW, 1_000);
+        }
+
+        history.advance(WINDOW, 1_000 * WINDOW);
+        assert_eq!(history.volume(), 0);
+        assert!(history.average() < 10);
+    }
+
+    #[test]
+    fn test_large_volume_does_not_overflow() {
+        let config = config(Some(3), Some(u128::MAX));
+        let mut history = VolumeHistory::default();
+        for window in 10..30 {
+            history.record(&config, window * WINDOW, u128::MAX);
+            history.record(&config, window * WINDOW + WINDOW / 2, u128::MAX);
+        }
+        assert_eq!(history.volume(), u128::MAX);
+        assert!(history.average() > 0);
+    }
+}
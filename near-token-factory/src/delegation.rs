@@ -0,0 +1,88 @@
+use crate::{account_id_from_token_address, ext, AclRole, Contract, ContractExt};
+use near_plugins::access_control_any;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, serde_json, AccountId, Gas, Promise, PromiseResult};
+use near_token_common as aurora_sdk;
+
+const GRANT_ROLE_COST: Gas = Gas(5_000_000_000_000);
+const ON_TOKEN_ROLE_GRANTED_COST: Gas = Gas(5_000_000_000_000);
+
+const ERR_NOT_TOKEN_SUPER_ADMIN: &str =
+    "ERR_NOT_TOKEN_SUPER_ADMIN: Factory is not the super-admin of the token.";
+
+/// Accounts managing a token on behalf of the ERC-20 issuer. They are set up when the
+/// token is created.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenDelegation {
+    /// Access control super-admin of the token. If `None`, the factory is the
+    /// super-admin.
+    pub super_admin: Option<AccountId>,
+    /// Accounts granted the `MetadataUpdater` role of the token.
+    pub metadata_updaters: Vec<AccountId>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Set the accounts managing `token` once it is created, or remove them if
+    /// `delegation` is `None`. It has no effect on tokens that are already created,
+    /// see `grant_token_role` instead. ONLY accounts with [`AclRole::Admin`] role can
+    /// call this method.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn set_token_delegation(
+        &mut self,
+        token: aurora_sdk::Address,
+        delegation: Option<TokenDelegation>,
+    ) {
+        let token_account_id = account_id_from_token_address(token);
+        match delegation {
+            Some(delegation) => self
+                .token_delegations
+                .insert(&token_account_id, &delegation),
+            None => self.token_delegations.remove(&token_account_id),
+        };
+    }
+
+    /// Returns the accounts managing `token` once it is created.
+    pub fn get_token_delegation(&self, token: aurora_sdk::Address) -> Option<TokenDelegation> {
+        self.token_delegations
+            .get(&account_id_from_token_address(token))
+    }
+
+    /// Grant `role` of an already created `token` to `account_id`. It fails if the
+    /// factory is not the super-admin of the token anymore, e.g. because super-admin
+    /// was delegated with `set_token_delegation`. Returns whether the role was newly
+    /// granted. ONLY accounts with [`AclRole::Admin`] role can call this method.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn grant_token_role(
+        &mut self,
+        token: aurora_sdk::Address,
+        role: String,
+        account_id: AccountId,
+    ) -> Promise {
+        let token_account_id = account_id_from_token_address(token);
+        self.get_token_info_or_panic(&token_account_id);
+
+        ext::ext_near_token::ext(token_account_id)
+            .with_static_gas(GRANT_ROLE_COST)
+            .acl_grant_role(role, account_id)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(ON_TOKEN_ROLE_GRANTED_COST)
+                    .on_token_role_granted(),
+            )
+    }
+
+    /// Callback of [`Self::grant_token_role`]. The token returns `None` if the factory
+    /// isn't allowed to grant roles, in which case this method fails.
+    #[private]
+    pub fn on_token_role_granted(&mut self) -> bool {
+        let granted = match env::promise_result(0) {
+            PromiseResult::Successful(output) => serde_json::from_slice::<Option<bool>>(&output)
+                .unwrap_or_else(|_| env::panic_str(ERR_NOT_TOKEN_SUPER_ADMIN)),
+            _ => None,
+        };
+        granted.unwrap_or_else(|| env::panic_str(ERR_NOT_TOKEN_SUPER_ADMIN))
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{batch_transfer_memo, Contract, ContractExt};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{env, near_bindgen, require, AccountId, Gas, Promise, PromiseResult};
+use near_token_common as aurora_sdk;
+
+const ON_DEPOSIT_BATCH_RESOLVED_COST: Gas = Gas(5_000_000_000_000);
+/// Gas limit of the transaction that executes the calls scheduled by the locker.
+const MAX_PREPAID_GAS: Gas = Gas(300_000_000_000_000);
+// The gas constants below mirror the ones of the locker.
+/// Gas used by the XCC router of the locker to execute the scheduled calls.
+const XCC_ROUTER_COST: Gas = Gas(30_000_000_000_000);
+/// Gas attached by the locker to `on_deposit_batch`, excluding the deposits in it.
+const ON_DEPOSIT_BATCH_BASE_COST: Gas = Gas(15_000_000_000_000);
+/// Gas attached by the locker to `on_deposit_batch` for each deposit.
+const ON_DEPOSIT_BATCH_ITEM_COST: Gas = Gas(10_000_000_000_000);
+/// Gas attached by the locker to its callback, excluding the deposits in the batch.
+const DEPOSIT_BATCH_CALLBACK_BASE_COST: Gas = Gas(15_000_000_000_000);
+/// Gas attached by the locker to its callback for each deposit.
+const DEPOSIT_BATCH_CALLBACK_ITEM_COST: Gas = Gas(5_000_000_000_000);
+/// Maximum number of deposits in a batch, such that the router, `on_deposit_batch` and
+/// the callback of the locker fit in the gas limit of a transaction.
+const MAX_DEPOSIT_BATCH_SIZE: usize = ((MAX_PREPAID_GAS.0
+    - XCC_ROUTER_COST.0
+    - ON_DEPOSIT_BATCH_BASE_COST.0
+    - DEPOSIT_BATCH_CALLBACK_BASE_COST.0)
+    / (ON_DEPOSIT_BATCH_ITEM_COST.0 + DEPOSIT_BATCH_CALLBACK_ITEM_COST.0))
+    as usize;
+
+const ERR_INVALID_BATCH_SIZE: &str = "ERR_INVALID_BATCH_SIZE: Deposit batch is empty or too large.";
+
+/// Single deposit of a batch sent by the locker, see [`Contract::on_deposit_batch`].
+#[derive(BorshDeserialize, BorshSerialize, Debug, Clone)]
+pub struct BatchDeposit {
+    pub token: aurora_sdk::Address,
+    pub receiver_id: AccountId,
+    pub amount: u128,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Method called by the locker when many tokens were deposited at once. Each
+    /// deposit is processed as in `on_deposit`, but a rejected or failed deposit
+    /// doesn't affect the others. Returns whether each deposit succeeded, borsh
+    /// encoded, so the locker refunds the failed ones. Fails if the batch is empty or
+    /// has more than `MAX_DEPOSIT_BATCH_SIZE` deposits, in which case all of them are
+    /// refunded.
+    #[payable]
+    pub fn on_deposit_batch(
+        &mut self,
+        #[serializer(borsh)] deposits: Vec<BatchDeposit>,
+        #[serializer(borsh)] nonce: u64,
+    ) -> Promise {
+        self.assert_locker();
+        require!(
+            !deposits.is_empty() && deposits.len() <= MAX_DEPOSIT_BATCH_SIZE,
+            ERR_INVALID_BATCH_SIZE
+        );
+        self.record_nonce(nonce);
+
+        let mut scheduled = Vec::with_capacity(deposits.len());
+        let mut promises: Option<Promise> = None;
+        for (index, deposit) in deposits.into_iter().enumerate() {
+            let memo = batch_transfer_memo(nonce, index);
+            match self.try_deposit(deposit.token, deposit.receiver_id, deposit.amount, memo) {
+                Ok(promise) => {
+                    scheduled.push(true);
+                    promises = Some(match promises {
+                        Some(promises) => promises.and(promise),
+                        None => promise,
+                    });
+                }
+                Err(err) => {
+                    env::log_str(&format!("Deposit {} rejected: {}", index, err));
+                    scheduled.push(false);
+                }
+            }
+        }
+
+        let callback = Self::ext(env::current_account_id())
+            .with_static_gas(ON_DEPOSIT_BATCH_RESOLVED_COST)
+            .on_deposit_batch_resolved(scheduled);
+        match promises {
+            Some(promises) => promises.then(callback),
+            None => callback,
+        }
+    }
+
+    /// Callback after minting the deposits of a batch. `scheduled` tells which deposits
+    /// were minted, in the same order as the results of the promises. Returns whether
+    /// each deposit succeeded.
+    #[private]
+    #[result_serializer(borsh)]
+    pub fn on_deposit_batch_resolved(&self, scheduled: Vec<bool>) -> Vec<bool> {
+        let mut result_index = 0;
+        scheduled
+            .into_iter()
+            .map(|is_scheduled| {
+                if !is_scheduled {
+                    return false;
+                }
+                let result = env::promise_result(result_index);
+                result_index += 1;
+                matches!(result, PromiseResult::Successful(_))
+            })
+            .collect()
+    }
+}
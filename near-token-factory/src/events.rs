@@ -0,0 +1,210 @@
+use crate::lifecycle::TokenStatus;
+use crate::pause::{Direction, PausedDirections};
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::AccountId;
+use near_token_common::{events, Address};
+
+/// Data to log when the directions in which a token is paused change. To log this
+/// event, call [`.emit()`](TokenPaused::emit).
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenPaused<'a> {
+    pub token_id: &'a AccountId,
+    pub paused: PausedDirections,
+    pub updated_by: &'a AccountId,
+    pub reason: &'a str,
+}
+
+impl TokenPaused<'_> {
+    pub fn emit(self) {
+        events::emit("token_paused", &self)
+    }
+}
+
+/// Data to log when the factory holds a withdrawal instead of sending it to the
+/// locker. To log this event, call [`.emit()`](WithdrawalHeld::emit).
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct WithdrawalHeld<'a> {
+    pub withdrawal_id: u64,
+    pub token_id: &'a AccountId,
+    /// `None` if the token doesn't report the sender of withdrawals.
+    pub sender_id: Option<&'a AccountId>,
+    pub receiver_id: &'a Address,
+    pub amount: U128,
+    pub unlock_at: Option<u64>,
+    pub transfer_id: u64,
+}
+
+impl WithdrawalHeld<'_> {
+    pub fn emit(self) {
+        events::emit("withdrawal_held", &self)
+    }
+}
+
+/// Data to log when a pending withdrawal cancelled by a guardian is minted back to
+/// its sender. To log this event, call [`.emit()`](WithdrawalCancelled::emit).
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct WithdrawalCancelled<'a> {
+    pub withdrawal_id: u64,
+    pub token_id: &'a AccountId,
+    pub sender_id: &'a AccountId,
+    pub amount: U128,
+    pub cancelled_by: &'a AccountId,
+}
+
+impl WithdrawalCancelled<'_> {
+    pub fn emit(self) {
+        events::emit("withdrawal_cancelled", &self)
+    }
+}
+
+/// Data to log after comparing the total supply of a token with the balance of the
+/// locker on Aurora. Values that could not be fetched are `None`. To log this event,
+/// call [`.emit()`](ReservesReconciliation::emit).
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReservesReconciliation<'a> {
+    pub token_id: &'a AccountId,
+    pub total_supply: Option<U128>,
+    pub locker_balance: Option<U128>,
+    /// Burnt tokens that are not sent to the locker yet, see
+    /// [`Contract::get_outstanding_withdrawals`](crate::Contract::get_outstanding_withdrawals).
+    pub outstanding_withdrawals: U128,
+    pub matched: bool,
+}
+
+impl ReservesReconciliation<'_> {
+    pub fn emit(self) {
+        events::emit("reserves_reconciliation", &self)
+    }
+}
+
+/// Data to log when the volume bridged for a token trips its circuit breaker. To log
+/// this event, call [`.emit()`](CircuitBreakerTripped::emit).
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CircuitBreakerTripped<'a> {
+    pub token_id: &'a AccountId,
+    pub direction: Direction,
+    pub current_volume: U128,
+    pub trailing_average: U128,
+}
+
+impl CircuitBreakerTripped<'_> {
+    pub fn emit(self) {
+        events::emit("circuit_breaker_tripped", &self)
+    }
+}
+
+/// Data to log when a fee is charged for bridging a token. To log this event, call
+/// [`.emit()`](BridgeFeeCharged::emit).
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BridgeFeeCharged<'a> {
+    pub token_id: &'a AccountId,
+    pub direction: Direction,
+    pub collector_id: &'a AccountId,
+    pub gross: U128,
+    pub fee: U128,
+    pub net: U128,
+}
+
+impl BridgeFeeCharged<'_> {
+    pub fn emit(self) {
+        events::emit("bridge_fee_charged", &self)
+    }
+}
+
+/// Data to log when the lifecycle status of a token changes. To log this event, call
+/// [`.emit()`](TokenStatusChanged::emit).
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenStatusChanged<'a> {
+    pub token_id: &'a AccountId,
+    pub old_status: TokenStatus,
+    pub new_status: TokenStatus,
+    pub updated_by: &'a AccountId,
+}
+
+impl TokenStatusChanged<'_> {
+    pub fn emit(self) {
+        events::emit("token_status_changed", &self)
+    }
+}
+
+/// Data to log when the account of a retired token is deleted. To log this event,
+/// call [`.emit()`](TokenDeleted::emit).
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenDeleted<'a> {
+    pub token_id: &'a AccountId,
+}
+
+impl TokenDeleted<'_> {
+    pub fn emit(self) {
+        events::emit("token_deleted", &self)
+    }
+}
+
+/// Data to log when the token binary used for new tokens changes. To log this event,
+/// call [`.emit()`](TokenBinaryActivated::emit).
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenBinaryActivated<'a> {
+    pub version: u32,
+    pub previous_version: u32,
+    pub activated_by: &'a AccountId,
+}
+
+impl TokenBinaryActivated<'_> {
+    pub fn emit(self) {
+        events::emit("token_binary_activated", &self)
+    }
+}
+
+/// Data to log when the factory calls the locker to unlock withdrawn tokens. To log
+/// this event, call [`.emit()`](WithdrawalSent::emit).
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct WithdrawalSent<'a> {
+    pub transfer_id: u64,
+    pub token_id: &'a AccountId,
+    pub receiver_id: &'a Address,
+    pub amount: U128,
+}
+
+impl WithdrawalSent<'_> {
+    pub fn emit(self) {
+        events::emit("withdrawal_sent", &self)
+    }
+}
+
+/// Data to log when the locker failed to unlock some withdrawals of a batch, so
+/// they are held as pending withdrawals. To log this event, call
+/// [`.emit()`](WithdrawalBatchFailed::emit).
+#[must_use]
+#[derive(Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct WithdrawalBatchFailed<'a> {
+    pub token_id: &'a AccountId,
+    pub transfer_ids: Vec<u64>,
+}
+
+impl WithdrawalBatchFailed<'_> {
+    pub fn emit(self) {
+        events::emit("withdrawal_batch_failed", &self)
+    }
+}
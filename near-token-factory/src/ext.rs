@@ -5,12 +5,30 @@ use near_token_common as aurora_sdk;
 pub trait ExtNearToken {
     fn upgrade_contract(&mut self, binary: near_sdk::json_types::Base64VecU8);
 
+    fn migrate(&mut self, bridged_token_info: Option<aurora_sdk::BridgedTokenInfo>);
+
     fn deposit(
         &mut self,
         receiver_id: near_sdk::AccountId,
         amount: near_sdk::json_types::U128,
         memo: Option<String>,
+        fee: Option<aurora_sdk::BridgeFee>,
     );
 
+    fn remint(
+        &mut self,
+        receiver_id: near_sdk::AccountId,
+        amount: near_sdk::json_types::U128,
+        memo: Option<String>,
+    );
+
+    fn set_withdraw_fee(&mut self, fee: Option<aurora_sdk::WithdrawFee>);
+
     fn update_metadata(&mut self, metadata: aurora_sdk::UpdateFungibleTokenMetadata);
+
+    fn ft_total_supply(&self) -> near_sdk::json_types::U128;
+
+    fn self_destruct(&mut self);
+
+    fn acl_grant_role(&mut self, role: String, account_id: near_sdk::AccountId) -> Option<bool>;
 }
@@ -0,0 +1,213 @@
+use crate::events::BridgeFeeCharged;
+use crate::pause::Direction;
+use crate::{
+    account_id_from_token_address, ext, AclRole, Contract, ContractExt, ERR_TOKEN_NOT_REGISTERED,
+};
+use near_plugins::access_control_any;
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{near_bindgen, require, AccountId, Balance, Gas, Promise};
+use near_token_common::{self as aurora_sdk, FeeSchedule, MAX_FEE_BPS};
+
+pub(crate) const SET_WITHDRAW_FEE_COST: Gas = Gas(5_000_000_000_000);
+
+const ERR_INVALID_FEE: &str = "ERR_INVALID_FEE: Fee can't exceed 10000 basis points.";
+
+/// Split of an amount into the fee and the amount received after the fee.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FeeQuote {
+    pub gross: U128,
+    pub fee: U128,
+    pub net: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Set the account that receives bridging fees, or stop charging fees if `None`.
+    /// The collector MUST be registered in every token for which fees are charged.
+    /// ONLY accounts with [`AclRole::Admin`] role can call this method.
+    ///
+    /// Withdrawal fees are charged by the tokens, so the change is pushed to every
+    /// deployed token. The gas attached must cover one call per token.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn set_fee_collector(&mut self, collector_id: Option<AccountId>) {
+        self.fee_collector = collector_id;
+        for token_account_id in self.tokens.keys() {
+            // The promise is scheduled even if it is not returned.
+            self.push_withdraw_fee(token_account_id);
+        }
+    }
+
+    /// Returns the account that receives bridging fees.
+    pub fn get_fee_collector(&self) -> Option<AccountId> {
+        self.fee_collector.clone()
+    }
+
+    /// Set the fee schedule used for tokens without their own schedule, or remove it
+    /// if `schedule` is `None`. ONLY accounts with [`AclRole::Admin`] role can call
+    /// this method. The withdrawal fee of every deployed token without its own
+    /// schedule is updated too, like in [`Self::set_fee_collector`].
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn set_default_fee_schedule(&mut self, schedule: Option<FeeSchedule>) {
+        if let Some(schedule) = schedule.as_ref() {
+            require!(schedule.fee_bps <= MAX_FEE_BPS, ERR_INVALID_FEE);
+        }
+        self.default_fee_schedule = schedule;
+        for token_account_id in self.tokens.keys() {
+            if self.fee_schedules.get(&token_account_id).is_none() {
+                self.push_withdraw_fee(token_account_id);
+            }
+        }
+    }
+
+    /// Returns the fee schedule used for tokens without their own schedule.
+    pub fn get_default_fee_schedule(&self) -> Option<FeeSchedule> {
+        self.default_fee_schedule.clone()
+    }
+
+    /// Set the fee schedule of `token`, or fall back to the default schedule if
+    /// `schedule` is `None`. ONLY accounts with [`AclRole::Admin`] role can call this
+    /// method. If the token is already deployed, its withdrawal fee is updated too.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn set_fee_schedule(&mut self, token: aurora_sdk::Address, schedule: Option<FeeSchedule>) {
+        let token_account_id = account_id_from_token_address(token);
+        match schedule {
+            Some(schedule) => {
+                require!(schedule.fee_bps <= MAX_FEE_BPS, ERR_INVALID_FEE);
+                self.fee_schedules.insert(&token_account_id, &schedule);
+            }
+            None => {
+                self.fee_schedules.remove(&token_account_id);
+            }
+        }
+
+        if self.tokens.get(&token_account_id).is_some() {
+            // The promise is scheduled even if it is not returned.
+            self.push_withdraw_fee(token_account_id);
+        }
+    }
+
+    /// Push the current fee collector and fee schedule of `token` to the token, which
+    /// charges the fee on withdrawals. This is a public method with no access
+    /// control, since the fee is determined by the factory. It can be used to retry
+    /// a push that failed.
+    pub fn sync_withdraw_fee(&mut self, token: aurora_sdk::Address) -> Promise {
+        let token_account_id = account_id_from_token_address(token);
+        require!(
+            self.tokens.get(&token_account_id).is_some(),
+            ERR_TOKEN_NOT_REGISTERED
+        );
+        self.push_withdraw_fee(token_account_id)
+    }
+
+    /// Returns the fee schedule of `token`, without falling back to the default one.
+    pub fn get_fee_schedule(&self, token: aurora_sdk::Address) -> Option<FeeSchedule> {
+        self.fee_schedules
+            .get(&account_id_from_token_address(token))
+    }
+
+    /// Returns the fee charged for bridging `amount` of `token` and the amount
+    /// received after the fee.
+    pub fn quote_fee(&self, token: aurora_sdk::Address, amount: U128) -> FeeQuote {
+        let fee = self.fee_for(&account_id_from_token_address(token), amount.0);
+        FeeQuote {
+            gross: amount,
+            fee: fee.into(),
+            net: (amount.0 - fee).into(),
+        }
+    }
+}
+
+impl Contract {
+    /// Fee charged for bridging `amount` of `token_account_id`. Zero if there is no
+    /// fee collector or schedule.
+    pub(crate) fn fee_for(&self, token_account_id: &AccountId, amount: Balance) -> Balance {
+        if self.fee_collector.is_none() {
+            return 0;
+        }
+        self.fee_schedules
+            .get(token_account_id)
+            .or_else(|| self.default_fee_schedule.clone())
+            .map_or(0, |schedule| schedule.fee(amount))
+    }
+
+    /// Fee charged by `token_account_id` on withdrawals, if any.
+    pub(crate) fn withdraw_fee_for(
+        &self,
+        token_account_id: &AccountId,
+    ) -> Option<aurora_sdk::WithdrawFee> {
+        let collector_id = self.fee_collector.clone()?;
+        let schedule = self
+            .fee_schedules
+            .get(token_account_id)
+            .or_else(|| self.default_fee_schedule.clone())?;
+        Some(aurora_sdk::WithdrawFee {
+            collector_id,
+            schedule,
+        })
+    }
+
+    /// Fee to mint to the collector for depositing `amount` of `token_account_id`, if
+    /// any. Emit `bridge_fee_charged` event if a fee is charged.
+    pub(crate) fn charge_deposit_fee(
+        &self,
+        token_account_id: &AccountId,
+        amount: Balance,
+    ) -> Option<aurora_sdk::BridgeFee> {
+        let fee = self.fee_for(token_account_id, amount);
+        if fee == 0 {
+            return None;
+        }
+        let collector_id = self.fee_collector.clone()?;
+
+        BridgeFeeCharged {
+            token_id: token_account_id,
+            direction: Direction::Deposit,
+            collector_id: &collector_id,
+            gross: amount.into(),
+            fee: fee.into(),
+            net: (amount - fee).into(),
+        }
+        .emit();
+
+        Some(aurora_sdk::BridgeFee {
+            collector_id,
+            amount: fee.into(),
+        })
+    }
+
+    fn push_withdraw_fee(&self, token_account_id: AccountId) -> Promise {
+        let fee = self.withdraw_fee_for(&token_account_id);
+        ext::ext_near_token::ext(token_account_id)
+            .with_static_gas(SET_WITHDRAW_FEE_COST)
+            .set_withdraw_fee(fee)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_token_common::FeeSchedule;
+
+    fn schedule(fee_bps: u16, min_fee: u128) -> FeeSchedule {
+        FeeSchedule {
+            fee_bps,
+            min_fee: min_fee.into(),
+        }
+    }
+
+    #[test]
+    fn test_fee_bps() {
+        assert_eq!(schedule(30, 0).fee(1_000_000), 3_000);
+        assert_eq!(schedule(30, 0).fee(u128::MAX), u128::MAX / 10_000 * 30 + 4);
+        assert_eq!(schedule(10_000, 0).fee(1_234), 1_234);
+    }
+
+    #[test]
+    fn test_min_fee() {
+        assert_eq!(schedule(30, 100).fee(1_000), 100);
+        assert_eq!(schedule(30, 100).fee(1_000_000), 3_000);
+        // The fee never exceeds the amount.
+        assert_eq!(schedule(30, 100).fee(50), 50);
+    }
+}
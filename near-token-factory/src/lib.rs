@@ -1,84 +1,262 @@
+use binaries::{BinaryUpload, StagedTokenBinary, TokenBinaryInfo};
+use circuit_breaker::{CircuitBreakerConfig, VolumeHistory};
+use delegation::TokenDelegation;
+use events::WithdrawalSent;
+use lifecycle::{TokenInfo, TokenStatus};
+use metadata::AdminMetadataFields;
+use near_plugins::{access_control, AccessControlRole, AccessControllable};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LazyOption, UnorderedMap};
+use near_sdk::collections::{LookupMap, LookupSet, UnorderedMap, Vector};
+use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::serde_json::json;
 use near_sdk::{
     env, near_bindgen, require, AccountId, Balance, BorshStorageKey, Gas, PanicOnDefault, Promise,
+    PromiseOrValue,
 };
 use near_token_common as aurora_sdk;
+use pause::{Direction, PausedDirections};
+use rate_limit::{RateLimit, WindowUsage, ERR_RATE_LIMIT_EXCEEDED};
+use withdrawal_batch::{BatchedWithdrawal, WithdrawalBatch, WithdrawalBatchConfig};
+use withdrawals::{LargeWithdrawalConfig, PendingWithdrawal};
+
+mod binaries;
+mod circuit_breaker;
+mod delegation;
+mod deposit_batch;
+mod events;
 mod ext;
+mod fees;
+mod lifecycle;
+mod metadata;
+mod pause;
+mod rate_limit;
+mod replay;
+mod reserves;
+mod time;
+mod upgrades;
+mod wasm;
+mod withdrawal_batch;
+mod withdrawals;
 
 const NEW_TOKEN_DEPOSIT_COST: Balance = 3_000_000_000_000_000_000_000_000;
 const TOKEN_STORAGE_DEPOSIT_COST: Balance = 1_250_000_000_000_000_000_000;
 const TOKEN_DEPLOYMENT_COST: Gas = Gas(5_000_000_000_000);
 const DEPOSIT_COST: Gas = Gas(5_000_000_000_000);
 const UPDATE_METADATA_COST: Gas = Gas(5_000_000_000_000);
+/// Gas kept for the locker call of a withdrawal when a lazy upgrade is scheduled. The
+/// locker call gets all the gas left.
+const WITHDRAW_COST: Gas = Gas(10_000_000_000_000);
 
 const ERR_ONLY_LOCKER: &str = "ERR_ONLY_LOCKER: Only locker can call this method.";
 const ERR_INVALID_ACCOUNT: &str =
     "ERR_INVALID_ACCOUNT: Account ID too large. Impossible to create token subcontracts.";
 const ERR_BINARY_NOT_AVAILABLE: &str = "ERR_BINARY_NOT_AVAILABLE: Token binary is not set.";
 const ERR_TOKEN_NOT_REGISTERED: &str = "ERR_TOKEN_NOT_REGISTERED: Token is not registered.";
+const ERR_TOKEN_NOT_ACTIVE: &str = "ERR_TOKEN_NOT_ACTIVE: Token is deprecated or retired.";
+const ERR_DEPOSIT_PAUSED: &str = "ERR_DEPOSIT_PAUSED: Deposits are paused for this token.";
+const ERR_AMOUNT_BELOW_FEE: &str = "ERR_AMOUNT_BELOW_FEE: Amount doesn't cover the bridging fee.";
 
-pub const WITHDRAW_SELECTOR: [u8; 4] = [0xd9, 0xca, 0xed, 0x12];
+/// Set in the transfer ids assigned by the factory to withdrawals of tokens that
+/// don't assign them, so they never collide with the ids assigned by tokens.
+const FACTORY_TRANSFER_ID_FLAG: u64 = 1 << 63;
+
+pub const WITHDRAW_SELECTOR: [u8; 4] = [0x2b, 0xdc, 0x0e, 0x66];
+pub const WITHDRAW_BATCH_SELECTOR: [u8; 4] = [0xfe, 0xf0, 0x7b, 0xfb];
+pub const BALANCE_OF_SELECTOR: [u8; 4] = [0x70, 0xa0, 0x82, 0x31];
 
 #[derive(BorshDeserialize, BorshSerialize, BorshStorageKey)]
 enum StorageKey {
-    TokenBinary,
+    TokenBinaries,
     TokenMap,
+    PausedTokens,
+    PendingWithdrawals,
+    RateLimits,
+    RateLimitUsage,
+    LargeWithdrawalConfigs,
+    CircuitBreakers,
+    VolumeHistory,
+    FeeSchedules,
+    TokenDelegations,
+    AdminMetadataFields,
+    TokenBinaryHistory,
+    BinaryUploadChunks,
+    WithdrawalBatches,
+    OutstandingWithdrawals,
+    UpgradingTokens,
+}
+
+#[derive(AccessControlRole, Deserialize, Serialize, Copy, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum AclRole {
+    /// Allowed to change the configuration of the factory.
+    Admin,
+    /// Allowed to pause and unpause bridging of individual tokens.
+    PauseManager,
+    /// Allowed to cancel pending withdrawals.
+    Guardian,
 }
 
+#[access_control(role_type(AclRole))]
 #[near_bindgen]
 #[derive(BorshSerialize, BorshDeserialize, PanicOnDefault)]
 pub struct Contract {
     /// Account id of the engine. It is expected to be `aurora`.
     aurora: AccountId,
-    /// WASM binary of the token contract.
-    token_binary: LazyOption<Vec<u8>>,
-    /// Version of the token contract.
+    /// WASM binaries of the token contract by version.
+    token_binaries: LookupMap<u32, Vec<u8>>,
+    /// Uploaded token binaries ordered by version, starting from version 1.
+    token_binary_history: Vector<TokenBinaryInfo>,
+    /// Version of the token contract used for new tokens. Zero if none is active.
     token_binary_version: u32,
-    /// Iterable map of deployed contracts and their current version.
-    tokens: UnorderedMap<AccountId, u32>,
+    /// Token binary waiting to be activated.
+    staged_token_binary: Option<StagedTokenBinary>,
+    /// Token binary being uploaded in chunks, if any.
+    binary_upload: Option<BinaryUpload>,
+    /// Chunks of the token binary being uploaded.
+    binary_upload_chunks: Vector<Vec<u8>>,
+    /// Whether anyone can upgrade outdated tokens with `upgrade_token`.
+    lazy_token_upgrades: bool,
+    /// Tokens whose upgrade is in progress.
+    upgrading_tokens: LookupSet<AccountId>,
+    /// Iterable map of deployed contracts and their current version and status.
+    tokens: UnorderedMap<AccountId, TokenInfo>,
     /// Address of the locker in aurora.
     locker: aurora_sdk::Address,
+    /// Directions in which bridging is paused for each token. Tokens that are not
+    /// present are not paused.
+    paused_tokens: LookupMap<AccountId, PausedDirections>,
+    /// Whether tokens are paused automatically when a reserves check fails.
+    auto_pause_on_reserve_mismatch: bool,
+    /// Amount of each token burnt on NEAR whose withdrawal is held or batched, so it
+    /// is still locked on Aurora.
+    outstanding_withdrawals: LookupMap<AccountId, Balance>,
+    /// Withdrawals whose tokens are already burnt, but are not sent to the locker yet.
+    pending_withdrawals: UnorderedMap<u64, PendingWithdrawal>,
+    /// Id assigned to the next pending withdrawal.
+    next_withdrawal_id: u64,
+    /// Rate limit for each token and direction. Tokens that are not present are not
+    /// limited.
+    rate_limits: LookupMap<(AccountId, Direction), RateLimit>,
+    /// Usage of the rate limit for each token and direction.
+    rate_limit_usage: LookupMap<(AccountId, Direction), WindowUsage>,
+    /// Threshold and delay for time-locking large withdrawals of each token.
+    large_withdrawal_configs: LookupMap<AccountId, LargeWithdrawalConfig>,
+    /// Circuit breaker for each token and direction.
+    circuit_breakers: LookupMap<(AccountId, Direction), CircuitBreakerConfig>,
+    /// Volume bridged for each token and direction with a circuit breaker.
+    volume_history: LookupMap<(AccountId, Direction), VolumeHistory>,
+    /// Account that receives bridging fees. No fees are charged if `None`.
+    fee_collector: Option<AccountId>,
+    /// Fee schedule used for tokens without their own schedule.
+    default_fee_schedule: Option<aurora_sdk::FeeSchedule>,
+    /// Fee schedule of each token.
+    fee_schedules: LookupMap<AccountId, aurora_sdk::FeeSchedule>,
+    /// Accounts managing each token once it is created.
+    token_delegations: LookupMap<AccountId, TokenDelegation>,
+    /// Metadata fields of each token that were set by an admin.
+    admin_metadata_fields: LookupMap<AccountId, AdminMetadataFields>,
+    /// Nonces of the messages from the locker that were processed.
+    processed_nonces: replay::NonceWindow,
+    /// Counter of the transfer ids assigned by the factory to withdrawals of tokens
+    /// that don't assign them.
+    next_transfer_id: u64,
+    /// Thresholds for flushing batched withdrawals. Withdrawals are sent one by one
+    /// if `None`.
+    withdrawal_batching: Option<WithdrawalBatchConfig>,
+    /// Withdrawals of each token waiting to be flushed to the locker.
+    withdrawal_batches: LookupMap<AccountId, WithdrawalBatch>,
 }
 
-// TODO: Add pausable
-// TODO: Add access control
 #[near_bindgen]
 impl Contract {
     /// Initializes the contract. The locker account id MUST be the NEAR
     /// representative of the Aurora address of the locker contract created
     /// using the Cross Contract Call interface.
+    ///
+    /// Optionally an account can be provided that is made access control super
+    /// admin. If `super_admin` is `None`, then the predecessor is made super admin.
     #[init]
-    pub fn new(aurora: AccountId, locker: aurora_sdk::Address) -> Self {
+    pub fn new(
+        aurora: AccountId,
+        locker: aurora_sdk::Address,
+        super_admin: Option<AccountId>,
+    ) -> Self {
         require!(
             env::current_account_id().as_str().len() + 1 + 40 <= 63,
             ERR_INVALID_ACCOUNT
         );
 
-        Self {
+        let mut contract = Self {
             aurora,
-            token_binary: LazyOption::new(StorageKey::TokenBinary, None),
+            token_binaries: LookupMap::new(StorageKey::TokenBinaries),
+            token_binary_history: Vector::new(StorageKey::TokenBinaryHistory),
             token_binary_version: 0,
+            staged_token_binary: None,
+            binary_upload: None,
+            binary_upload_chunks: Vector::new(StorageKey::BinaryUploadChunks),
+            lazy_token_upgrades: false,
+            upgrading_tokens: LookupSet::new(StorageKey::UpgradingTokens),
             tokens: UnorderedMap::new(StorageKey::TokenMap),
             locker,
-        }
+            paused_tokens: LookupMap::new(StorageKey::PausedTokens),
+            auto_pause_on_reserve_mismatch: false,
+            outstanding_withdrawals: LookupMap::new(StorageKey::OutstandingWithdrawals),
+            pending_withdrawals: UnorderedMap::new(StorageKey::PendingWithdrawals),
+            next_withdrawal_id: 0,
+            rate_limits: LookupMap::new(StorageKey::RateLimits),
+            rate_limit_usage: LookupMap::new(StorageKey::RateLimitUsage),
+            large_withdrawal_configs: LookupMap::new(StorageKey::LargeWithdrawalConfigs),
+            circuit_breakers: LookupMap::new(StorageKey::CircuitBreakers),
+            volume_history: LookupMap::new(StorageKey::VolumeHistory),
+            fee_collector: None,
+            default_fee_schedule: None,
+            fee_schedules: LookupMap::new(StorageKey::FeeSchedules),
+            token_delegations: LookupMap::new(StorageKey::TokenDelegations),
+            admin_metadata_fields: LookupMap::new(StorageKey::AdminMetadataFields),
+            processed_nonces: Default::default(),
+            next_transfer_id: 0,
+            withdrawal_batching: None,
+            withdrawal_batches: LookupMap::new(StorageKey::WithdrawalBatches),
+            __acl: Default::default(),
+        };
+
+        let super_admin = super_admin.unwrap_or_else(env::predecessor_account_id);
+        require!(
+            contract.acl_init_super_admin(super_admin),
+            "Failed to add initial acl super-admin",
+        );
+
+        contract
     }
 
-    /// Set WASM binary for the token contracts. This increases the token binary version,
-    /// so all deployed contracts SHOULD be upgraded after calling this function. ONLY the
-    /// `Owner` role can call this method.
+    /// Set WASM binary for the token contracts and activate it immediately. This
+    /// increases the token binary version, so all deployed contracts SHOULD be upgraded
+    /// after calling this function. Use `stage_token_binary` to activate it after a
+    /// delay instead. ONLY the `Owner` role can call this method.
     pub fn set_token_binary(&mut self, binary: near_sdk::json_types::Base64VecU8) {
         // TODO: Replace with Owner
         near_sdk::assert_self();
 
-        self.token_binary.set(&binary.into());
-        self.token_binary_version += 1;
+        let version = self.register_token_binary(binary.into());
+        self.activate_token_binary_version(version);
     }
 
-    /// Get the most recent binary version or fails if no binary is available.
+    /// Same as `set_token_binary`, but for a legacy token binary that doesn't export
+    /// all methods required by the factory, such as the first token binary. It is
+    /// only meant to reproduce tokens deployed with such binaries, e.g. to test their
+    /// upgrade. Fails if the binary exports all required methods. ONLY the `Owner`
+    /// role can call this method.
+    pub fn set_legacy_token_binary(&mut self, binary: near_sdk::json_types::Base64VecU8) {
+        // TODO: Replace with Owner
+        near_sdk::assert_self();
+
+        let version = self.register_legacy_token_binary(binary.into());
+        self.activate_token_binary_version(version);
+    }
+
+    /// Get the active binary or fails if no binary is available.
     fn get_token_binary(&self) -> Vec<u8> {
-        match self.token_binary.get() {
+        match self.token_binaries.get(&self.token_binary_version) {
             None => env::panic_str(ERR_BINARY_NOT_AVAILABLE),
             Some(binary) => binary,
         }
@@ -86,55 +264,93 @@ impl Contract {
 
     /// Create a new token by deploying the current binary in a sub-account. This method
     /// can only be called by the locker.
+    ///
+    /// Every message from the locker carries a unique `nonce`. Messages whose nonce was
+    /// already processed are rejected.
+    ///
+    /// If a delegation was set for the token with `set_token_delegation`, its accounts
+    /// are made super-admin and metadata updaters of the token. The withdrawal fee of
+    /// the token is set as well, if any.
     #[payable]
     pub fn create_token(
         &mut self,
         #[serializer(borsh)] token_address: aurora_sdk::Address,
+        #[serializer(borsh)] nonce: u64,
     ) -> Promise {
         self.assert_locker();
+        self.record_nonce(nonce);
 
-        let token_account_id = account_id_from_token_address(token_address);
+        let token_account_id = account_id_from_token_address(token_address.clone());
         let binary = self.get_token_binary();
+        let delegation = self.token_delegations.get(&token_account_id);
+        let withdraw_fee = self.withdraw_fee_for(&token_account_id);
+
+        self.tokens.insert(
+            &token_account_id,
+            &TokenInfo {
+                version: self.token_binary_version,
+                status: TokenStatus::Active,
+            },
+        );
 
-        self.tokens
-            .insert(&token_account_id, &self.token_binary_version);
-
-        Promise::new(token_account_id)
+        let deployment = Promise::new(token_account_id)
             .create_account()
             .transfer(NEW_TOKEN_DEPOSIT_COST)
             .deploy_contract(binary)
             .function_call(
                 "new".to_string(),
-                json!({ "super_admin": None::<AccountId> })
-                    .to_string()
-                    .into_bytes(),
+                json!({
+                    "super_admin": delegation.as_ref().and_then(|d| d.super_admin.clone()),
+                    "metadata_updaters": delegation.map(|d| d.metadata_updaters),
+                    "erc20_address": token_address,
+                    "aurora": self.aurora,
+                    "locker": self.locker,
+                    "binary_version": self.token_binary_version,
+                })
+                .to_string()
+                .into_bytes(),
                 TOKEN_STORAGE_DEPOSIT_COST,
                 TOKEN_DEPLOYMENT_COST,
-            )
+            );
+        match withdraw_fee {
+            Some(fee) => deployment.function_call(
+                "set_withdraw_fee".to_string(),
+                json!({ "fee": fee }).to_string().into_bytes(),
+                0,
+                fees::SET_WITHDRAW_FEE_COST,
+            ),
+            None => deployment,
+        }
     }
 
     /// Method called by the locker when new tokens were deposited. The same amount of
     /// tokens is minted in the equivalent NEP-141 contract. If such contract doesn't
-    /// exist it is deployed.
+    /// exist it is deployed. Fails if the token is not active, deposits are paused for
+    /// the token, the amount exceeds its rate limit or doesn't cover the fee, in which
+    /// case the locker refunds the sender.
+    ///
+    /// If a fee is charged, the receiver gets the amount net of the fee and the fee is
+    /// minted to the fee collector.
+    ///
+    /// The nonce of the message is the id of the transfer. It is included in the memo
+    /// of the minted tokens as `deposit:<nonce>`, so the mint can be traced back to
+    /// the deposit in Aurora.
     #[payable]
     pub fn on_deposit(
         &mut self,
         #[serializer(borsh)] token: aurora_sdk::Address,
         #[serializer(borsh)] receiver_id: AccountId,
         #[serializer(borsh)] amount: u128,
+        #[serializer(borsh)] nonce: u64,
     ) -> Promise {
         self.assert_locker();
+        self.record_nonce(nonce);
 
-        let token_account_id = account_id_from_token_address(token);
-
-        require!(
-            self.tokens.get(&token_account_id).is_some(),
-            ERR_TOKEN_NOT_REGISTERED
-        );
-
-        ext::ext_near_token::ext(token_account_id)
-            .with_static_gas(DEPOSIT_COST)
-            .deposit(receiver_id, amount.into(), None)
+        let token_account_id = account_id_from_token_address(token.clone());
+        let deposit = self
+            .try_deposit(token, receiver_id, amount, transfer_memo(nonce))
+            .unwrap_or_else(|err| env::panic_str(err));
+        self.with_lazy_upgrade(&token_account_id, deposit, DEPOSIT_COST)
     }
 
     /// Method invoked by each individual token when an account id calls `withdraw`.
@@ -147,18 +363,77 @@ impl Contract {
     ///
     /// This is a public method with no access control. However calling will only grant
     /// withdraw privileges to the token associated with the caller if any. If the caller
-    /// is not a previously deployed token, this method will fail.
+    /// is not a previously deployed token, this method will fail. Withdrawals are
+    /// accepted for deprecated and retired tokens as well.
+    ///
+    /// Withdrawals of at least the configured threshold are time-locked. They are held
+    /// by the factory and can be executed with `execute_pending_withdrawal` once the
+    /// delay passed, or cancelled by a guardian. Withdrawals are also held if they are
+    /// paused for the token, including by its circuit breaker, or the amount exceeds
+    /// its rate limit.
+    ///
+    /// Withdrawal fees are charged by the token, which transfers the fee to the fee
+    /// collector and only burns the rest, so `amount` is already net of the fee.
+    ///
+    /// `transfer_id` is assigned by the token when the tokens are burnt and is unique
+    /// per token. It is sent to the locker and logged in the events of the factory,
+    /// so the withdrawal can be traced from the burn to the unlock in Aurora. Deposits
+    /// use the nonces of the locker as transfer ids, which are a separate id space.
+    ///
+    /// With lazy upgrades enabled, an outdated token is upgraded by this method,
+    /// `on_deposit` and `update_token_metadata` if they have enough gas left.
+    ///
+    /// `sender_id` and `transfer_id` are optional, since tokens deployed before they
+    /// were reported call this method without them. Held withdrawals without a sender
+    /// can't be cancelled. Withdrawals without a transfer id are assigned one by the
+    /// factory, with the highest bit set.
     pub fn on_withdraw(
         &mut self,
+        sender_id: Option<AccountId>,
         receiver_id: aurora_sdk::Address,
         amount: near_sdk::json_types::U128,
-    ) -> Promise {
-        let token_id = address_from_token_account_id(env::predecessor_account_id());
-
-        let input = abi_encode_withdraw(&token_id, &receiver_id, amount.into());
+        transfer_id: Option<u64>,
+    ) -> PromiseOrValue<()> {
+        let token_account_id = env::predecessor_account_id();
+        require!(
+            self.tokens.get(&token_account_id).is_some(),
+            ERR_TOKEN_NOT_REGISTERED
+        );
 
-        aurora_sdk::aurora::ext_aurora::ext(self.aurora.clone())
-            .call(aurora_sdk::aurora::call_args(self.locker.clone(), input))
+        let transfer_id = transfer_id.unwrap_or_else(|| {
+            let transfer_id = self.next_transfer_id | FACTORY_TRANSFER_ID_FLAG;
+            self.next_transfer_id += 1;
+            transfer_id
+        });
+
+        self.record_volume(&token_account_id, Direction::Withdraw, amount.0);
+
+        // The upgrade is scheduled on its own, since nothing else calls the token.
+        let _ = self.lazy_upgrade(&token_account_id, WITHDRAW_COST);
+
+        let unlock_at = self.large_withdrawal_unlock_at(&token_account_id, amount);
+        if unlock_at.is_some()
+            || self.is_paused(&token_account_id, Direction::Withdraw)
+            || !self.try_consume_rate_limit(&token_account_id, Direction::Withdraw, amount.0)
+        {
+            self.hold_withdrawal(
+                token_account_id.clone(),
+                sender_id,
+                receiver_id,
+                amount,
+                unlock_at,
+                transfer_id,
+            );
+            PromiseOrValue::Value(())
+        } else {
+            self.send_withdrawal(
+                &token_account_id,
+                sender_id,
+                &receiver_id,
+                amount.into(),
+                transfer_id,
+            )
+        }
     }
 
     /// Representative account id of the locker in Aurora.
@@ -167,13 +442,16 @@ impl Contract {
     }
 
     /// Method that allows updating the metadata of a particular token. This method can only
-    /// be called by the locker.
+    /// be called by the locker. Only the fields available in the ERC-20 contract are
+    /// updated, the remaining ones are set with `update_tokens_admin_metadata`.
     pub fn update_token_metadata(
         &mut self,
         #[serializer(borsh)] token: aurora_sdk::Address,
         #[serializer(borsh)] metadata: ERC20Metadata,
+        #[serializer(borsh)] nonce: u64,
     ) -> Promise {
         self.assert_locker();
+        self.record_nonce(nonce);
 
         let token_account_id = account_id_from_token_address(token);
 
@@ -181,18 +459,96 @@ impl Contract {
             env::panic_str(ERR_TOKEN_NOT_REGISTERED);
         }
 
-        ext::ext_near_token::ext(token_account_id)
+        let update = ext::ext_near_token::ext(token_account_id.clone())
             .with_static_gas(UPDATE_METADATA_COST)
             .update_metadata(aurora_sdk::UpdateFungibleTokenMetadata {
                 name: Some(metadata.name),
                 symbol: Some(metadata.symbol),
                 decimals: Some(metadata.decimals),
                 ..Default::default()
-            })
+            });
+        self.with_lazy_upgrade(&token_account_id, update, UPDATE_METADATA_COST)
     }
 }
 
 impl Contract {
+    /// Check that `amount` of `token` can be deposited and mint it for `receiver_id`.
+    /// Returns the error if the deposit is rejected, in which case no state is changed.
+    fn try_deposit(
+        &mut self,
+        token: aurora_sdk::Address,
+        receiver_id: AccountId,
+        amount: u128,
+        memo: String,
+    ) -> Result<Promise, &'static str> {
+        let token_account_id = account_id_from_token_address(token);
+
+        match self.tokens.get(&token_account_id) {
+            None => return Err(ERR_TOKEN_NOT_REGISTERED),
+            Some(info) if info.status != TokenStatus::Active => return Err(ERR_TOKEN_NOT_ACTIVE),
+            Some(_) => {}
+        }
+        if self.is_paused(&token_account_id, Direction::Deposit) {
+            return Err(ERR_DEPOSIT_PAUSED);
+        }
+        if self.fee_for(&token_account_id, amount) >= amount {
+            return Err(ERR_AMOUNT_BELOW_FEE);
+        }
+        if !self.try_consume_rate_limit(&token_account_id, Direction::Deposit, amount) {
+            return Err(ERR_RATE_LIMIT_EXCEEDED);
+        }
+        self.record_volume(&token_account_id, Direction::Deposit, amount);
+
+        let fee = self.charge_deposit_fee(&token_account_id, amount);
+        let fee_amount = fee.as_ref().map_or(0, |fee| fee.amount.0);
+
+        Ok(ext::ext_near_token::ext(token_account_id)
+            .with_static_gas(DEPOSIT_COST)
+            .deposit(receiver_id, (amount - fee_amount).into(), Some(memo), fee))
+    }
+
+    /// Call the locker in Aurora to unlock `amount` tokens for `receiver_id`. If
+    /// withdrawal batching is enabled, the withdrawal is added to the batch of the
+    /// token instead, which is sent once it is full or flushed.
+    ///
+    /// Emit `withdrawal_sent` event when the withdrawal is sent.
+    fn send_withdrawal(
+        &mut self,
+        token_account_id: &AccountId,
+        sender_id: Option<AccountId>,
+        receiver_id: &aurora_sdk::Address,
+        amount: u128,
+        transfer_id: u64,
+    ) -> PromiseOrValue<()> {
+        if self.withdrawal_batching.is_some() {
+            return match self.batch_withdrawal(
+                token_account_id,
+                sender_id,
+                receiver_id,
+                amount,
+                transfer_id,
+            ) {
+                Some(flush) => flush.into(),
+                None => PromiseOrValue::Value(()),
+            };
+        }
+
+        WithdrawalSent {
+            transfer_id,
+            token_id: token_account_id,
+            receiver_id,
+            amount: amount.into(),
+        }
+        .emit();
+
+        let token_id = address_from_token_account_id(token_account_id.clone());
+        let input = abi_encode_withdraw(&token_id, receiver_id, amount, transfer_id);
+
+        aurora_sdk::aurora::ext_aurora::ext(self.aurora.clone())
+            .call(aurora_sdk::aurora::call_args(self.locker.clone(), input))
+            .into()
+    }
+
     fn assert_locker(&self) {
         require!(
             env::predecessor_account_id() == self.locker_account_id(),
@@ -220,15 +576,103 @@ fn abi_encode_withdraw(
     token_id: &aurora_sdk::Address,
     receiver_id: &aurora_sdk::Address,
     amount: u128,
+    transfer_id: u64,
 ) -> Vec<u8> {
-    let mut buffer = [0u8; 4 + 32 + 32 + 32];
+    let mut buffer = [0u8; 4 + 32 + 32 + 32 + 32];
     buffer[0..4].copy_from_slice(&WITHDRAW_SELECTOR);
     buffer[16..36].copy_from_slice(&token_id.0);
     buffer[48..68].copy_from_slice(&receiver_id.0);
     buffer[84..100].copy_from_slice(&amount.to_be_bytes());
+    buffer[124..132].copy_from_slice(&transfer_id.to_be_bytes());
+    buffer.to_vec()
+}
+
+/// Manual implementation of abi encoding of
+/// `withdrawBatch(address,address[],uint256[],uint64[])` for efficiency.
+fn abi_encode_withdraw_batch(
+    token_id: &aurora_sdk::Address,
+    withdrawals: &[BatchedWithdrawal],
+) -> Vec<u8> {
+    // Each array is encoded as its length followed by one word per element, after
+    // the head with the token and the offsets of the three arrays.
+    let array_size = 32 * (withdrawals.len() + 1);
+    let mut buffer = vec![0u8; 4 + 4 * 32 + 3 * array_size];
+    buffer[0..4].copy_from_slice(&WITHDRAW_BATCH_SELECTOR);
+    buffer[16..36].copy_from_slice(&token_id.0);
+
+    let receivers = 4 + 4 * 32;
+    let amounts = receivers + array_size;
+    let transfer_ids = amounts + array_size;
+    let length = (withdrawals.len() as u64).to_be_bytes();
+    for (index, array) in [receivers, amounts, transfer_ids].into_iter().enumerate() {
+        let offset = ((array - 4) as u64).to_be_bytes();
+        buffer[4 + 32 * index + 56..4 + 32 * index + 64].copy_from_slice(&offset);
+        buffer[array + 24..array + 32].copy_from_slice(&length);
+    }
+
+    for (index, withdrawal) in withdrawals.iter().enumerate() {
+        let word = 32 * (index + 1);
+        buffer[receivers + word + 12..receivers + word + 32]
+            .copy_from_slice(&withdrawal.receiver_id.0);
+        buffer[amounts + word + 16..amounts + word + 32]
+            .copy_from_slice(&withdrawal.amount.0.to_be_bytes());
+        buffer[transfer_ids + word + 24..transfer_ids + word + 32]
+            .copy_from_slice(&withdrawal.transfer_id.to_be_bytes());
+    }
+    buffer
+}
+
+/// Memo of the tokens minted for the deposit with transfer id `nonce`. It is prefixed
+/// with `deposit:`, since tokens use a separate id space for withdrawals.
+fn transfer_memo(nonce: u64) -> String {
+    format!("deposit:{}", nonce)
+}
+
+/// Memo of the tokens minted for item `index` of the batched deposit with transfer id
+/// `nonce`.
+fn batch_transfer_memo(nonce: u64, index: usize) -> String {
+    format!("deposit:{}/{}", nonce, index)
+}
+
+/// Manual implementation of abi encoding of `balanceOf(address)` for efficiency.
+fn abi_encode_balance_of(owner: &aurora_sdk::Address) -> Vec<u8> {
+    let mut buffer = [0u8; 4 + 32];
+    buffer[0..4].copy_from_slice(&BALANCE_OF_SELECTOR);
+    buffer[16..36].copy_from_slice(&owner.0);
     buffer.to_vec()
 }
 
+/// Decode an abi encoded `uint256`. Returns `None` if the input is not a single
+/// word or the value doesn't fit in `u128`.
+fn abi_decode_u128(output: &[u8]) -> Option<u128> {
+    if output.len() != 32 || output[0..16].iter().any(|&byte| byte != 0) {
+        return None;
+    }
+    let mut buffer = [0u8; 16];
+    buffer.copy_from_slice(&output[16..32]);
+    Some(u128::from_be_bytes(buffer))
+}
+
+/// Decode an abi encoded `bool[]`, as returned by a function. Returns `None` if the
+/// input is not a single dynamic array of booleans.
+fn abi_decode_bool_array(output: &[u8]) -> Option<Vec<bool>> {
+    let mut words = output.chunks(32).map(abi_decode_u128);
+    if words.next()?? != 32 {
+        return None;
+    }
+    let length = usize::try_from(words.next()??).ok()?;
+    if length.checked_mul(32)?.checked_add(64)? != output.len() {
+        return None;
+    }
+    words
+        .map(|word| match word? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone, BorshDeserialize, BorshSerialize)]
 pub struct ERC20Metadata {
     name: String,
@@ -239,11 +683,15 @@ pub struct ERC20Metadata {
 #[cfg(test)]
 mod tests {
     use crate::aurora_sdk::Address;
-    use crate::{abi_encode_withdraw, WITHDRAW_SELECTOR};
+    use crate::{
+        abi_decode_bool_array, abi_decode_u128, abi_encode_balance_of, abi_encode_withdraw,
+        abi_encode_withdraw_batch, BatchedWithdrawal, BALANCE_OF_SELECTOR, WITHDRAW_BATCH_SELECTOR,
+        WITHDRAW_SELECTOR,
+    };
 
     #[test]
     /// Check withdraw selector is properly computed. Function signature is:
-    /// "withdraw(address,address,uint256)"
+    /// "withdraw(address,address,uint256,uint64)"
     fn test_withdraw_select() {
         assert_eq!(
             &ethabi::short_signature(
@@ -251,7 +699,8 @@ mod tests {
                 &[
                     ethabi::ParamType::Address,
                     ethabi::ParamType::Address,
-                    ethabi::ParamType::Uint(256)
+                    ethabi::ParamType::Uint(256),
+                    ethabi::ParamType::Uint(64)
                 ],
             ),
             &WITHDRAW_SELECTOR
@@ -267,14 +716,142 @@ mod tests {
             12, 12, 12, 12, 34, 34, 34, 34, 56, 56, 56, 56, 78, 78, 78, 78, 90, 90, 90, 90,
         ];
         let amount = 0x998877665544332211u128;
+        let transfer_id = 0x1122334455667788u64;
 
         assert_eq!(
-            &abi_encode_withdraw(&Address(token_id), &Address(receiver_id), amount,)[4..],
+            &abi_encode_withdraw(
+                &Address(token_id),
+                &Address(receiver_id),
+                amount,
+                transfer_id
+            )[4..],
             &ethabi::encode(&[
                 ethabi::Token::Address(ethabi::Address::try_from(&token_id).unwrap()),
                 ethabi::Token::Address(ethabi::Address::try_from(&receiver_id).unwrap()),
                 ethabi::Token::Uint(ethabi::Uint::from(amount)),
+                ethabi::Token::Uint(ethabi::Uint::from(transfer_id)),
             ])
         );
     }
+
+    #[test]
+    /// Check withdrawBatch selector is properly computed. Function signature is:
+    /// "withdrawBatch(address,address[],uint256[],uint64[])"
+    fn test_withdraw_batch_selector() {
+        assert_eq!(
+            &ethabi::short_signature(
+                "withdrawBatch",
+                &[
+                    ethabi::ParamType::Address,
+                    ethabi::ParamType::Array(Box::new(ethabi::ParamType::Address)),
+                    ethabi::ParamType::Array(Box::new(ethabi::ParamType::Uint(256))),
+                    ethabi::ParamType::Array(Box::new(ethabi::ParamType::Uint(64))),
+                ],
+            ),
+            &WITHDRAW_BATCH_SELECTOR
+        );
+    }
+
+    #[test]
+    fn test_abi_encode_withdraw_batch() {
+        let token_id = [
+            12, 34, 56, 78, 90, 12, 34, 56, 78, 90, 12, 34, 56, 78, 90, 12, 34, 56, 78, 90,
+        ];
+        let withdrawals = vec![
+            BatchedWithdrawal {
+                sender_id: None,
+                receiver_id: Address([12; 20]),
+                amount: 0x998877665544332211u128.into(),
+                transfer_id: 0x1122334455667788,
+            },
+            BatchedWithdrawal {
+                sender_id: None,
+                receiver_id: Address([34; 20]),
+                amount: u128::MAX.into(),
+                transfer_id: 7,
+            },
+        ];
+
+        assert_eq!(
+            &abi_encode_withdraw_batch(&Address(token_id), &withdrawals)[4..],
+            &ethabi::encode(&[
+                ethabi::Token::Address(ethabi::Address::try_from(&token_id).unwrap()),
+                ethabi::Token::Array(
+                    withdrawals
+                        .iter()
+                        .map(|w| ethabi::Token::Address(ethabi::Address::from(w.receiver_id.0)))
+                        .collect()
+                ),
+                ethabi::Token::Array(
+                    withdrawals
+                        .iter()
+                        .map(|w| ethabi::Token::Uint(ethabi::Uint::from(w.amount.0)))
+                        .collect()
+                ),
+                ethabi::Token::Array(
+                    withdrawals
+                        .iter()
+                        .map(|w| ethabi::Token::Uint(ethabi::Uint::from(w.transfer_id)))
+                        .collect()
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    /// Check balanceOf selector is properly computed. Function signature is:
+    /// "balanceOf(address)"
+    fn test_balance_of_selector() {
+        assert_eq!(
+            &ethabi::short_signature("balanceOf", &[ethabi::ParamType::Address]),
+            &BALANCE_OF_SELECTOR
+        );
+    }
+
+    #[test]
+    fn test_abi_encode_balance_of() {
+        let owner = [
+            12, 34, 56, 78, 90, 12, 34, 56, 78, 90, 12, 34, 56, 78, 90, 12, 34, 56, 78, 90,
+        ];
+
+        assert_eq!(
+            &abi_encode_balance_of(&Address(owner))[4..],
+            &ethabi::encode(&[ethabi::Token::Address(
+                ethabi::Address::try_from(&owner).unwrap()
+            )])
+        );
+    }
+
+    #[test]
+    fn test_abi_decode_u128() {
+        let amount = 0x998877665544332211u128;
+        let encoded = ethabi::encode(&[ethabi::Token::Uint(ethabi::Uint::from(amount))]);
+        assert_eq!(abi_decode_u128(&encoded), Some(amount));
+
+        let encoded = ethabi::encode(&[ethabi::Token::Uint(
+            ethabi::Uint::from(u128::MAX) + ethabi::Uint::from(1),
+        )]);
+        assert_eq!(abi_decode_u128(&encoded), None);
+        assert_eq!(abi_decode_u128(&encoded[1..]), None);
+    }
+
+    #[test]
+    fn test_abi_decode_bool_array() {
+        let results = vec![true, false, false, true];
+        let encoded = ethabi::encode(&[ethabi::Token::Array(
+            results
+                .iter()
+                .map(|&result| ethabi::Token::Bool(result))
+                .collect(),
+        )]);
+        assert_eq!(abi_decode_bool_array(&encoded), Some(results));
+
+        let encoded = ethabi::encode(&[ethabi::Token::Array(vec![])]);
+        assert_eq!(abi_decode_bool_array(&encoded), Some(vec![]));
+
+        let mut encoded = ethabi::encode(&[ethabi::Token::Array(vec![ethabi::Token::Bool(true)])]);
+        assert_eq!(abi_decode_bool_array(&encoded[..64]), None);
+        encoded[95] = 2;
+        assert_eq!(abi_decode_bool_array(&encoded), None);
+    }
 }
@@ -0,0 +1,158 @@
+use crate::events::{TokenDeleted, TokenStatusChanged};
+use crate::pause::Direction;
+use crate::{
+    account_id_from_token_address, ext, AclRole, Contract, ContractExt, ERR_TOKEN_NOT_REGISTERED,
+};
+use near_plugins::access_control_any;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, require, AccountId, Gas, Promise};
+use near_token_common as aurora_sdk;
+
+const SELF_DESTRUCT_COST: Gas = Gas(5_000_000_000_000);
+const ON_TOKEN_DELETED_COST: Gas = Gas(10_000_000_000_000);
+
+const ERR_TOKEN_RETIRED: &str = "ERR_TOKEN_RETIRED: Status of a retired token can't change.";
+const ERR_TOKEN_NOT_RETIRED: &str = "ERR_TOKEN_NOT_RETIRED: Only retired tokens can be deleted.";
+const ERR_OUTSTANDING_WITHDRAWALS: &str =
+    "ERR_OUTSTANDING_WITHDRAWALS: Token has held or batched withdrawals.";
+
+/// Lifecycle status of a token deployed by the factory.
+#[derive(
+    BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug, Clone, Copy, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub enum TokenStatus {
+    /// Deposits and withdrawals are accepted.
+    Active,
+    /// Deposits are rejected, withdrawals are still accepted.
+    Deprecated,
+    /// Same as deprecated, but the status can't change anymore.
+    Retired,
+}
+
+/// Information about a token deployed by the factory.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenInfo {
+    /// Version of the token binary.
+    pub version: u32,
+    pub status: TokenStatus,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Set the lifecycle status of `token`. A retired token can't change its status.
+    /// ONLY accounts with [`AclRole::Admin`] role can call this method.
+    ///
+    /// Emit `token_status_changed` event.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn set_token_status(&mut self, token: aurora_sdk::Address, status: TokenStatus) {
+        let token_account_id = account_id_from_token_address(token);
+        let mut info = self.get_token_info_or_panic(&token_account_id);
+        require!(info.status != TokenStatus::Retired, ERR_TOKEN_RETIRED);
+
+        TokenStatusChanged {
+            token_id: &token_account_id,
+            old_status: info.status,
+            new_status: status,
+            updated_by: &env::predecessor_account_id(),
+        }
+        .emit();
+
+        info.status = status;
+        self.tokens.insert(&token_account_id, &info);
+    }
+
+    /// Delete the account of a retired `token` with zero total supply, which sends its
+    /// balance to the factory. The token is removed from the registered tokens once
+    /// the account is deleted. It fails while withdrawals of the token are held or
+    /// batched, since they must be executed or cancelled first. ONLY accounts with
+    /// [`AclRole::Admin`] role can call this method.
+    ///
+    /// The storage deposits of accounts still registered in the token are part of its
+    /// balance, so they are sent to the factory as well. Holders must unregister with
+    /// `storage_unregister` before the token is deleted to get their deposit back.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn delete_token(&mut self, token: aurora_sdk::Address) -> Promise {
+        let token_account_id = account_id_from_token_address(token);
+        require!(
+            self.get_token_info_or_panic(&token_account_id).status == TokenStatus::Retired,
+            ERR_TOKEN_NOT_RETIRED
+        );
+        require!(
+            self.outstanding_withdrawals(&token_account_id) == 0,
+            ERR_OUTSTANDING_WITHDRAWALS
+        );
+
+        ext::ext_near_token::ext(token_account_id.clone())
+            .with_static_gas(SELF_DESTRUCT_COST)
+            .self_destruct()
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(ON_TOKEN_DELETED_COST)
+                    .on_token_deleted(token_account_id),
+            )
+    }
+
+    /// Callback after the account of a token is deleted. Removes the token, its
+    /// configuration and every other state kept for it from the factory, so a token
+    /// deployed again for the same ERC-20 starts afresh. Returns whether the account
+    /// was deleted.
+    ///
+    /// Emit `token_deleted` event if the account was deleted.
+    #[private]
+    pub fn on_token_deleted(&mut self, token_account_id: AccountId) -> bool {
+        if !near_sdk::is_promise_success() {
+            return false;
+        }
+
+        self.tokens.remove(&token_account_id);
+        self.upgrading_tokens.remove(&token_account_id);
+        self.paused_tokens.remove(&token_account_id);
+        self.outstanding_withdrawals.remove(&token_account_id);
+        self.withdrawal_batches.remove(&token_account_id);
+        self.large_withdrawal_configs.remove(&token_account_id);
+        self.fee_schedules.remove(&token_account_id);
+        self.token_delegations.remove(&token_account_id);
+        self.admin_metadata_fields.remove(&token_account_id);
+        for direction in [Direction::Deposit, Direction::Withdraw] {
+            let key = (token_account_id.clone(), direction);
+            self.rate_limits.remove(&key);
+            self.rate_limit_usage.remove(&key);
+            self.circuit_breakers.remove(&key);
+            self.volume_history.remove(&key);
+        }
+
+        TokenDeleted {
+            token_id: &token_account_id,
+        }
+        .emit();
+        true
+    }
+
+    /// Returns the lifecycle status of `token`, if it is registered.
+    pub fn get_token_status(&self, token: aurora_sdk::Address) -> Option<TokenStatus> {
+        self.tokens
+            .get(&account_id_from_token_address(token))
+            .map(|info| info.status)
+    }
+
+    /// Returns up to `limit` registered tokens and their information, after skipping
+    /// the first `skip` ones.
+    pub fn get_tokens(&self, skip: u64, limit: u64) -> Vec<(AccountId, TokenInfo)> {
+        self.tokens
+            .iter()
+            .skip(skip as usize)
+            .take(limit as usize)
+            .collect()
+    }
+}
+
+impl Contract {
+    pub(crate) fn get_token_info_or_panic(&self, token_account_id: &AccountId) -> TokenInfo {
+        self.tokens
+            .get(token_account_id)
+            .unwrap_or_else(|| env::panic_str(ERR_TOKEN_NOT_REGISTERED))
+    }
+}
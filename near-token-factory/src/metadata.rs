@@ -0,0 +1,129 @@
+use crate::{
+    account_id_from_token_address, ext, AclRole, Contract, ContractExt, UPDATE_METADATA_COST,
+};
+use near_plugins::access_control_any;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::Base64VecU8;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId, Gas};
+use near_token_common as aurora_sdk;
+
+const ON_ADMIN_METADATA_UPDATED_COST: Gas = Gas(5_000_000_000_000);
+
+/// Metadata fields forwarded by the locker from the ERC-20 contract.
+const LOCKER_SOURCED_FIELDS: [&str; 3] = ["name", "symbol", "decimals"];
+
+/// Metadata fields of a token that can't be read from the ERC-20 contract, and are
+/// set by an admin instead.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AdminMetadataUpdate {
+    pub token: aurora_sdk::Address,
+    pub icon: Option<String>,
+    pub reference: Option<String>,
+    pub reference_hash: Option<Base64VecU8>,
+}
+
+/// Metadata fields of a token that were set by an admin.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AdminMetadataFields {
+    pub icon: bool,
+    pub reference: bool,
+    pub reference_hash: bool,
+}
+
+/// Source of each metadata field of a token, see [`Contract::get_metadata_sources`].
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MetadataSources {
+    /// Fields forwarded by the locker from the ERC-20 contract.
+    pub locker: Vec<String>,
+    /// Fields set by an admin of the factory.
+    pub admin: Vec<String>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Update the metadata fields that the locker can't provide for one or many
+    /// tokens. Fields that are `None` are left unchanged. Each token is updated in a
+    /// separate promise, so one failing update doesn't affect the others. Fields are
+    /// only recorded as set by an admin once the token applied the update. ONLY
+    /// accounts with [`AclRole::Admin`] role can call this method.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn update_tokens_admin_metadata(&mut self, updates: Vec<AdminMetadataUpdate>) {
+        for update in updates {
+            let token_account_id = account_id_from_token_address(update.token);
+            self.get_token_info_or_panic(&token_account_id);
+
+            let fields = AdminMetadataFields {
+                icon: update.icon.is_some(),
+                reference: update.reference.is_some(),
+                reference_hash: update.reference_hash.is_some(),
+            };
+
+            ext::ext_near_token::ext(token_account_id.clone())
+                .with_static_gas(UPDATE_METADATA_COST)
+                .update_metadata(aurora_sdk::UpdateFungibleTokenMetadata {
+                    icon: update.icon,
+                    reference: update.reference,
+                    reference_hash: update.reference_hash,
+                    ..Default::default()
+                })
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(ON_ADMIN_METADATA_UPDATED_COST)
+                        .on_admin_metadata_updated(token_account_id, fields),
+                );
+        }
+    }
+
+    /// Callback after a token applied an update of [`Self::update_tokens_admin_metadata`].
+    /// Records `fields` as set by an admin if the update succeeded. Returns whether it
+    /// succeeded.
+    #[private]
+    pub fn on_admin_metadata_updated(
+        &mut self,
+        token_account_id: AccountId,
+        fields: AdminMetadataFields,
+    ) -> bool {
+        if !near_sdk::is_promise_success() {
+            return false;
+        }
+
+        let mut recorded = self
+            .admin_metadata_fields
+            .get(&token_account_id)
+            .unwrap_or_default();
+        recorded.icon |= fields.icon;
+        recorded.reference |= fields.reference;
+        recorded.reference_hash |= fields.reference_hash;
+        self.admin_metadata_fields
+            .insert(&token_account_id, &recorded);
+        true
+    }
+
+    /// Returns which metadata fields of `token` are forwarded by the locker and which
+    /// ones were set by an admin.
+    pub fn get_metadata_sources(&self, token: aurora_sdk::Address) -> MetadataSources {
+        let fields = self
+            .admin_metadata_fields
+            .get(&account_id_from_token_address(token))
+            .unwrap_or_default();
+
+        let admin = [
+            ("icon", fields.icon),
+            ("reference", fields.reference),
+            ("reference_hash", fields.reference_hash),
+        ]
+        .into_iter()
+        .filter(|(_, is_set)| *is_set)
+        .map(|(field, _)| field.to_string())
+        .collect();
+
+        MetadataSources {
+            locker: LOCKER_SOURCED_FIELDS.map(String::from).to_vec(),
+            admin,
+        }
+    }
+}
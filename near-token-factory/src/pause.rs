@@ -0,0 +1,118 @@
+use crate::circuit_breaker::ERR_CIRCUIT_BREAKER_TRIPPED;
+use crate::events::TokenPaused;
+use crate::{account_id_from_token_address, AclRole, Contract, ContractExt};
+use near_plugins::access_control_any;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, require, AccountId};
+use near_token_common as aurora_sdk;
+
+/// Direction of a transfer through the bridge.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Direction {
+    /// From Aurora to NEAR.
+    Deposit,
+    /// From NEAR to Aurora.
+    Withdraw,
+}
+
+/// Directions in which bridging a token is paused.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug, Clone, Copy, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PausedDirections {
+    pub deposit: bool,
+    pub withdraw: bool,
+}
+
+impl PausedDirections {
+    pub fn get(&self, direction: Direction) -> bool {
+        match direction {
+            Direction::Deposit => self.deposit,
+            Direction::Withdraw => self.withdraw,
+        }
+    }
+
+    /// Set the flag for `direction`, or for both directions if `None`.
+    pub fn set(&mut self, direction: Option<Direction>, paused: bool) {
+        match direction {
+            Some(Direction::Deposit) => self.deposit = paused,
+            Some(Direction::Withdraw) => self.withdraw = paused,
+            None => {
+                self.deposit = paused;
+                self.withdraw = paused;
+            }
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Pause bridging `token` in `direction`, or in both directions if `None`. While
+    /// paused, deposits are rejected and withdrawals are held by the factory. ONLY
+    /// accounts with [`AclRole::PauseManager`] role can call this method.
+    ///
+    /// Emit `token_paused` event.
+    #[access_control_any(roles(AclRole::PauseManager))]
+    pub fn pause_token(&mut self, token: aurora_sdk::Address, direction: Option<Direction>) {
+        let token_account_id = account_id_from_token_address(token);
+        self.set_paused(&token_account_id, direction, true, "manual");
+    }
+
+    /// Resume bridging `token` in `direction`, or in both directions if `None`. ONLY
+    /// accounts with [`AclRole::PauseManager`] role can call this method. Directions
+    /// paused by a circuit breaker can only be resumed by an admin with
+    /// `reset_circuit_breaker`.
+    ///
+    /// Emit `token_paused` event.
+    #[access_control_any(roles(AclRole::PauseManager))]
+    pub fn unpause_token(&mut self, token: aurora_sdk::Address, direction: Option<Direction>) {
+        let token_account_id = account_id_from_token_address(token);
+        let directions = match direction {
+            Some(direction) => vec![direction],
+            None => vec![Direction::Deposit, Direction::Withdraw],
+        };
+        for direction in directions {
+            require!(
+                !self.circuit_breaker_tripped(&token_account_id, direction),
+                ERR_CIRCUIT_BREAKER_TRIPPED
+            );
+        }
+        self.set_paused(&token_account_id, direction, false, "manual");
+    }
+
+    /// Returns the directions in which bridging `token` is paused.
+    pub fn token_paused(&self, token: aurora_sdk::Address) -> PausedDirections {
+        self.paused_tokens
+            .get(&account_id_from_token_address(token))
+            .unwrap_or_default()
+    }
+}
+
+impl Contract {
+    pub(crate) fn is_paused(&self, token_account_id: &AccountId, direction: Direction) -> bool {
+        self.paused_tokens
+            .get(token_account_id)
+            .map_or(false, |paused| paused.get(direction))
+    }
+
+    pub(crate) fn set_paused(
+        &mut self,
+        token_account_id: &AccountId,
+        direction: Option<Direction>,
+        paused: bool,
+        reason: &str,
+    ) {
+        let mut directions = self.paused_tokens.get(token_account_id).unwrap_or_default();
+        directions.set(direction, paused);
+        self.paused_tokens.insert(token_account_id, &directions);
+
+        TokenPaused {
+            token_id: token_account_id,
+            paused: directions,
+            updated_by: &env::predecessor_account_id(),
+            reason,
+        }
+        .emit();
+    }
+}
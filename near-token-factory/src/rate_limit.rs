@@ -0,0 +1,226 @@
+use crate::pause::Direction;
+use crate::time::{self, NANOS_PER_SECOND};
+use crate::{account_id_from_token_address, AclRole, Contract, ContractExt};
+use near_plugins::access_control_any;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId, Balance};
+use near_token_common as aurora_sdk;
+
+pub(crate) const ERR_RATE_LIMIT_EXCEEDED: &str =
+    "ERR_RATE_LIMIT_EXCEEDED: Amount exceeds the rate limit for this token.";
+
+/// Maximum amount of tokens that can be bridged in one direction within a rolling
+/// window, e.g. an hour or a day.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RateLimit {
+    pub amount: U128,
+    pub window_sec: u64,
+}
+
+/// Usage of a rate limit. The rolling window is approximated with two consecutive
+/// fixed windows, where the amount of the previous window is weighted by its overlap
+/// with the rolling window ending now.
+#[derive(BorshDeserialize, BorshSerialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct WindowUsage {
+    /// Start of the current fixed window, in nanoseconds.
+    pub window_start: u64,
+    /// Amount bridged in the previous fixed window.
+    pub previous: Balance,
+    /// Amount bridged in the current fixed window.
+    pub current: Balance,
+}
+
+impl WindowUsage {
+    /// Move the fixed windows forward so that `now` is inside the current one.
+    pub fn advance(&mut self, window: u64, now: u64) {
+        let elapsed = now.saturating_sub(self.window_start);
+        if elapsed >= 2 * window {
+            self.previous = 0;
+            self.current = 0;
+            self.window_start = now - now % window;
+        } else if elapsed >= window {
+            self.previous = self.current;
+            self.current = 0;
+            self.window_start += window;
+        }
+    }
+
+    /// Amount bridged within the rolling window ending at `now`. The windows MUST be
+    /// advanced before calling this method.
+    pub fn used(&self, window: u64, now: u64) -> Balance {
+        let elapsed = now.saturating_sub(self.window_start).min(window);
+        let previous_weight = u128::from(window - elapsed);
+        let window = u128::from(window);
+        // Equivalent to `previous * previous_weight / window` without overflowing.
+        let previous = self.previous / window * previous_weight
+            + self.previous % window * previous_weight / window;
+        previous + self.current
+    }
+
+    /// Record `amount` if it fits within `limit`. Amounts larger than the limit are
+    /// always rejected, even if the rolling window is empty.
+    pub fn try_consume(&mut self, limit: Balance, window: u64, now: u64, amount: Balance) -> bool {
+        self.advance(window, now);
+        let used = self.used(window, now);
+        if matches!(used.checked_add(amount), Some(total) if total <= limit) {
+            self.current += amount;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Current state of a rate limit, see [`Contract::get_rate_limit_usage`].
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RateLimitUsage {
+    pub limit: RateLimit,
+    pub used: U128,
+    pub available: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Set the rate limit for bridging `token` in `direction`, or remove it if `limit`
+    /// is `None`. ONLY accounts with [`AclRole::Admin`] role can call this method.
+    ///
+    /// Deposits over the limit are rejected, so the locker refunds them. Withdrawals
+    /// over the limit are held, since the tokens are already burnt, and can be executed
+    /// with `execute_pending_withdrawal` once the window has capacity again. A single
+    /// amount larger than the limit is never accepted, so it must be cancelled by a
+    /// guardian or the limit raised.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn set_rate_limit(
+        &mut self,
+        token: aurora_sdk::Address,
+        direction: Direction,
+        limit: Option<RateLimit>,
+    ) {
+        let key = (account_id_from_token_address(token), direction);
+        match limit {
+            Some(limit) => {
+                time::assert_valid_window(limit.window_sec);
+                self.rate_limits.insert(&key, &limit);
+            }
+            None => {
+                self.rate_limits.remove(&key);
+                self.rate_limit_usage.remove(&key);
+            }
+        }
+    }
+
+    /// Returns the rate limit for bridging `token` in `direction`, if any.
+    pub fn get_rate_limit(
+        &self,
+        token: aurora_sdk::Address,
+        direction: Direction,
+    ) -> Option<RateLimit> {
+        self.rate_limits
+            .get(&(account_id_from_token_address(token), direction))
+    }
+
+    /// Returns the amount bridged within the current rolling window for `token` in
+    /// `direction` and the amount still available, if there is a rate limit.
+    pub fn get_rate_limit_usage(
+        &self,
+        token: aurora_sdk::Address,
+        direction: Direction,
+    ) -> Option<RateLimitUsage> {
+        let key = (account_id_from_token_address(token), direction);
+        let limit = self.rate_limits.get(&key)?;
+        let window = limit.window_sec * NANOS_PER_SECOND;
+        let now = env::block_timestamp();
+
+        let mut usage = self.rate_limit_usage.get(&key).unwrap_or_default();
+        usage.advance(window, now);
+        let used = usage.used(window, now);
+
+        Some(RateLimitUsage {
+            available: limit.amount.0.saturating_sub(used).into(),
+            used: used.into(),
+            limit,
+        })
+    }
+}
+
+impl Contract {
+    /// Record `amount` bridged for `token_account_id` in `direction`. Returns `false`
+    /// without recording anything if the amount exceeds the rate limit.
+    pub(crate) fn try_consume_rate_limit(
+        &mut self,
+        token_account_id: &AccountId,
+        direction: Direction,
+        amount: Balance,
+    ) -> bool {
+        let key = (token_account_id.clone(), direction);
+        let limit = match self.rate_limits.get(&key) {
+            Some(limit) => limit,
+            None => return true,
+        };
+
+        let mut usage = self.rate_limit_usage.get(&key).unwrap_or_default();
+        let consumed = usage.try_consume(
+            limit.amount.into(),
+            limit.window_sec * NANOS_PER_SECOND,
+            env::block_timestamp(),
+            amount,
+        );
+        if consumed {
+            self.rate_limit_usage.insert(&key, &usage);
+        }
+        consumed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WindowUsage;
+
+    const WINDOW: u64 = 100;
+
+    #[test]
+    fn test_window_usage_within_limit() {
+        let mut usage = WindowUsage::default();
+        assert!(usage.try_consume(10, WINDOW, 1_000, 6));
+        assert!(usage.try_consume(10, WINDOW, 1_050, 4));
+        assert!(!usage.try_consume(10, WINDOW, 1_099, 1));
+        assert_eq!(usage.used(WINDOW, 1_099), 10);
+    }
+
+    #[test]
+    fn test_window_usage_rolls_over() {
+        let mut usage = WindowUsage::default();
+        assert!(usage.try_consume(10, WINDOW, 1_000, 10));
+
+        // Halfway through the next window only half of the previous one counts.
+        assert!(!usage.try_consume(10, WINDOW, 1_150, 6));
+        assert!(usage.try_consume(10, WINDOW, 1_150, 5));
+        assert_eq!(usage.used(WINDOW, 1_150), 10);
+
+        // After two windows everything is forgotten.
+        usage.advance(WINDOW, 1_400);
+        assert_eq!(usage.used(WINDOW, 1_400), 0);
+    }
+
+    #[test]
+    fn test_window_usage_oversized_amount() {
+        let mut usage = WindowUsage::default();
+        assert!(!usage.try_consume(10, WINDOW, 1_000, 25));
+        assert_eq!(usage.used(WINDOW, 1_000), 0);
+        assert!(!usage.try_consume(10, WINDOW, 1_200, 11));
+        assert!(usage.try_consume(10, WINDOW, 1_200, 10));
+    }
+
+    #[test]
+    fn test_window_usage_large_amounts() {
+        let day = 86_400 * 1_000_000_000;
+        let mut usage = WindowUsage::default();
+        assert!(usage.try_consume(u128::MAX, day, day, u128::MAX / 2));
+        usage.advance(day, day + day / 2 + day);
+        assert_eq!(usage.used(day, day + day / 2 + day), u128::MAX / 4);
+    }
+}
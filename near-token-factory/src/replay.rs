@@ -0,0 +1,139 @@
+use crate::{Contract, ContractExt};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{near_bindgen, require};
+
+/// Number of nonces starting at the watermark whose processing is tracked.
+const NONCE_WINDOW: u64 = 1024;
+const WORD_BITS: u64 = u64::BITS as u64;
+
+const ERR_NONCE_ALREADY_PROCESSED: &str =
+    "ERR_NONCE_ALREADY_PROCESSED: Message from the locker was already processed.";
+const ERR_NONCE_EXPIRED: &str =
+    "ERR_NONCE_EXPIRED: Message from the locker is too old to be processed.";
+
+/// Nonces of the messages from the locker that were processed. Only the nonces in a
+/// window of `NONCE_WINDOW` nonces starting at the watermark are tracked, so the
+/// state is bounded. Nonces below the watermark can't be processed anymore, and the
+/// watermark advances as higher nonces are processed.
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct NonceWindow {
+    /// Lowest nonce that can still be processed.
+    watermark: u64,
+    /// Ring buffer with a bit for each nonce in the window, set if it was processed.
+    processed: Vec<u64>,
+}
+
+impl NonceWindow {
+    fn bit(nonce: u64) -> (usize, u64) {
+        let index = nonce % NONCE_WINDOW;
+        ((index / WORD_BITS) as usize, 1 << (index % WORD_BITS))
+    }
+
+    fn is_set(&self, nonce: u64) -> bool {
+        let (word, mask) = Self::bit(nonce);
+        self.processed
+            .get(word)
+            .map_or(false, |bits| bits & mask != 0)
+    }
+
+    /// Returns whether `nonce` was processed or is below the watermark.
+    pub fn is_processed(&self, nonce: u64) -> bool {
+        nonce < self.watermark || (nonce - self.watermark < NONCE_WINDOW && self.is_set(nonce))
+    }
+
+    /// Record `nonce` as processed, advancing the watermark if it is past the window.
+    /// Returns `false` if it was already processed or is below the watermark.
+    pub fn insert(&mut self, nonce: u64) -> bool {
+        if self.is_processed(nonce) {
+            return false;
+        }
+        if self.processed.is_empty() {
+            self.processed = vec![0; (NONCE_WINDOW / WORD_BITS) as usize];
+        }
+
+        let watermark = nonce.saturating_sub(NONCE_WINDOW - 1);
+        if watermark.saturating_sub(self.watermark) >= NONCE_WINDOW {
+            self.processed.iter_mut().for_each(|bits| *bits = 0);
+            self.watermark = watermark;
+        }
+        while self.watermark < watermark {
+            let (word, mask) = Self::bit(self.watermark);
+            self.processed[word] &= !mask;
+            self.watermark += 1;
+        }
+
+        let (word, mask) = Self::bit(nonce);
+        self.processed[word] |= mask;
+        true
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns whether the message from the locker with `nonce` was processed.
+    /// Messages that failed, e.g. rejected deposits, are not recorded. Nonces below
+    /// the watermark are reported as processed, since they can't be processed anymore.
+    pub fn is_nonce_processed(&self, nonce: u64) -> bool {
+        self.processed_nonces.is_processed(nonce)
+    }
+
+    /// Returns the lowest nonce of a message from the locker that can still be
+    /// processed.
+    pub fn nonce_watermark(&self) -> u64 {
+        self.processed_nonces.watermark
+    }
+}
+
+impl Contract {
+    /// Record the nonce of a message from the locker. Fails if it was already
+    /// processed, so a message scheduled twice is only applied once. Fails as well if
+    /// it is more than `NONCE_WINDOW` nonces behind the highest processed nonce.
+    pub(crate) fn record_nonce(&mut self, nonce: u64) {
+        require!(nonce >= self.processed_nonces.watermark, ERR_NONCE_EXPIRED);
+        require!(
+            self.processed_nonces.insert(nonce),
+            ERR_NONCE_ALREADY_PROCESSED
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NonceWindow, NONCE_WINDOW};
+
+    #[test]
+    fn test_nonce_window_rejects_duplicates() {
+        let mut nonces = NonceWindow::default();
+        assert!(nonces.insert(1));
+        assert!(nonces.insert(0));
+        assert!(!nonces.insert(1));
+        assert!(nonces.is_processed(0));
+        assert!(!nonces.is_processed(2));
+        assert_eq!(nonces.watermark, 0);
+    }
+
+    #[test]
+    fn test_nonce_window_advances_watermark() {
+        let mut nonces = NonceWindow::default();
+        assert!(nonces.insert(3));
+        assert!(nonces.insert(NONCE_WINDOW + 5));
+        assert_eq!(nonces.watermark, 6);
+
+        // Nonces below the watermark are expired, the rest of the window is kept.
+        assert!(nonces.is_processed(4));
+        assert!(!nonces.insert(4));
+        assert!(!nonces.is_processed(6));
+        assert!(nonces.insert(6));
+        assert!(!nonces.is_processed(NONCE_WINDOW + 3));
+    }
+
+    #[test]
+    fn test_nonce_window_large_jump() {
+        let mut nonces = NonceWindow::default();
+        assert!(nonces.insert(10));
+        assert!(nonces.insert(u64::MAX));
+        assert_eq!(nonces.watermark, u64::MAX - NONCE_WINDOW + 1);
+        assert!(!nonces.insert(u64::MAX));
+        assert!(!nonces.is_processed(u64::MAX - NONCE_WINDOW + 10));
+    }
+}
@@ -0,0 +1,160 @@
+use crate::events::ReservesReconciliation;
+use crate::{
+    abi_decode_u128, abi_encode_balance_of, account_id_from_token_address, ext, AclRole, Contract,
+    ContractExt, ERR_TOKEN_NOT_REGISTERED,
+};
+use near_plugins::access_control_any;
+use near_plugins::AccessControllable;
+use near_sdk::borsh::BorshDeserialize;
+use near_sdk::json_types::U128;
+use near_sdk::{
+    env, near_bindgen, require, serde_json, AccountId, Balance, Gas, Promise, PromiseResult,
+};
+use near_token_common as aurora_sdk;
+
+const FT_TOTAL_SUPPLY_COST: Gas = Gas(5_000_000_000_000);
+const LOCKER_BALANCE_VIEW_COST: Gas = Gas(20_000_000_000_000);
+const ON_RESERVES_CHECKED_COST: Gas = Gas(10_000_000_000_000);
+
+#[near_bindgen]
+impl Contract {
+    /// Compare the total supply of the NEP-141 representative of `token` with the
+    /// balance of the locker in the ERC-20 contract on Aurora. The locker balance
+    /// MUST equal the total supply plus the outstanding withdrawals, except while
+    /// transfers are in flight. This is a public method with no access control.
+    ///
+    /// Emit `reserves_reconciliation` event. If auto-pausing is enabled, the values
+    /// differ and the caller has [`AclRole::PauseManager`] role, the token is paused
+    /// in both directions. Checks by other accounts never pause, so a check racing
+    /// with an in-flight transfer can't be used to halt the bridge.
+    pub fn check_reserves(&mut self, token: aurora_sdk::Address) -> Promise {
+        let token_account_id = account_id_from_token_address(token.clone());
+        require!(
+            self.tokens.get(&token_account_id).is_some(),
+            ERR_TOKEN_NOT_REGISTERED
+        );
+
+        let total_supply = ext::ext_near_token::ext(token_account_id.clone())
+            .with_static_gas(FT_TOTAL_SUPPLY_COST)
+            .ft_total_supply();
+        let locker_balance = aurora_sdk::aurora::ext_aurora::ext(self.aurora.clone())
+            .with_static_gas(LOCKER_BALANCE_VIEW_COST)
+            .view(aurora_sdk::aurora::view_args(
+                self.locker.clone(),
+                token,
+                abi_encode_balance_of(&self.locker),
+            ));
+
+        let auto_pause = self.auto_pause_on_reserve_mismatch
+            && self.acl_has_role(AclRole::PauseManager.into(), env::predecessor_account_id());
+
+        total_supply.and(locker_balance).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(ON_RESERVES_CHECKED_COST)
+                .on_reserves_checked(token_account_id, auto_pause),
+        )
+    }
+
+    /// Callback of [`Self::check_reserves`]. Returns whether the locker balance
+    /// matches the total supply plus the outstanding withdrawals.
+    #[private]
+    pub fn on_reserves_checked(&mut self, token_account_id: AccountId, auto_pause: bool) -> bool {
+        let total_supply = match env::promise_result(0) {
+            PromiseResult::Successful(output) => serde_json::from_slice::<U128>(&output).ok(),
+            _ => None,
+        };
+        let locker_balance = match env::promise_result(1) {
+            PromiseResult::Successful(output) => {
+                match aurora_sdk::TransactionStatus::try_from_slice(&output) {
+                    Ok(aurora_sdk::TransactionStatus::Succeed(output)) => {
+                        abi_decode_u128(&output).map(U128)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        let outstanding_withdrawals = self.outstanding_withdrawals(&token_account_id);
+
+        let matched = match (total_supply, locker_balance) {
+            (Some(total_supply), Some(locker_balance)) => {
+                total_supply.0.checked_add(outstanding_withdrawals) == Some(locker_balance.0)
+            }
+            _ => false,
+        };
+
+        ReservesReconciliation {
+            token_id: &token_account_id,
+            total_supply,
+            locker_balance,
+            outstanding_withdrawals: outstanding_withdrawals.into(),
+            matched,
+        }
+        .emit();
+
+        // Only pause when both values are known, a failed view call is not evidence of
+        // a mismatch.
+        if !matched && total_supply.is_some() && locker_balance.is_some() && auto_pause {
+            self.set_paused(&token_account_id, None, true, "reserves_mismatch");
+        }
+
+        matched
+    }
+
+    /// Enable or disable pausing tokens automatically when [`Self::check_reserves`]
+    /// finds a mismatch. ONLY accounts with [`AclRole::Admin`] role can call this
+    /// method.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn set_auto_pause_on_reserve_mismatch(&mut self, enabled: bool) {
+        self.auto_pause_on_reserve_mismatch = enabled;
+    }
+
+    /// Returns whether tokens are paused automatically on reserves mismatch.
+    pub fn auto_pause_on_reserve_mismatch(&self) -> bool {
+        self.auto_pause_on_reserve_mismatch
+    }
+
+    /// Returns the amount of `token` burnt on NEAR whose withdrawal is held or
+    /// batched, so it is still locked on Aurora.
+    pub fn get_outstanding_withdrawals(&self, token: aurora_sdk::Address) -> U128 {
+        self.outstanding_withdrawals(&account_id_from_token_address(token))
+            .into()
+    }
+}
+
+impl Contract {
+    pub(crate) fn outstanding_withdrawals(&self, token_account_id: &AccountId) -> Balance {
+        self.outstanding_withdrawals
+            .get(token_account_id)
+            .unwrap_or(0)
+    }
+
+    /// Record that `amount` burnt tokens are held or batched instead of being sent to
+    /// the locker.
+    pub(crate) fn add_outstanding_withdrawal(
+        &mut self,
+        token_account_id: &AccountId,
+        amount: Balance,
+    ) {
+        let outstanding = self.outstanding_withdrawals(token_account_id) + amount;
+        self.outstanding_withdrawals
+            .insert(token_account_id, &outstanding);
+    }
+
+    /// Record that `amount` held or batched tokens are sent to the locker or minted
+    /// back on NEAR.
+    pub(crate) fn remove_outstanding_withdrawal(
+        &mut self,
+        token_account_id: &AccountId,
+        amount: Balance,
+    ) {
+        let outstanding = self.outstanding_withdrawals(token_account_id) - amount;
+        if outstanding == 0 {
+            self.outstanding_withdrawals.remove(token_account_id);
+        } else {
+            self.outstanding_withdrawals
+                .insert(token_account_id, &outstanding);
+        }
+    }
+}
@@ -0,0 +1,28 @@
+//! Conversions between durations configured in seconds and block timestamps, which
+//! are expressed in nanoseconds.
+
+use near_sdk::require;
+
+pub const NANOS_PER_SECOND: u64 = 1_000_000_000;
+/// Longest configurable window, so that windows in nanoseconds can't overflow.
+pub const MAX_WINDOW_SEC: u64 = 366 * 24 * 60 * 60;
+
+/// Longest configurable delay, so that timestamps in nanoseconds can't overflow.
+pub const MAX_DELAY_SEC: u64 = 366 * 24 * 60 * 60;
+
+pub const ERR_INVALID_WINDOW: &str =
+    "ERR_INVALID_WINDOW: Window duration must be between 1 second and 366 days.";
+pub const ERR_INVALID_DELAY: &str = "ERR_INVALID_DELAY: Delay can't exceed 366 days.";
+
+/// Check that a window of `window_sec` seconds is within bounds.
+pub fn assert_valid_window(window_sec: u64) {
+    require!(
+        (1..=MAX_WINDOW_SEC).contains(&window_sec),
+        ERR_INVALID_WINDOW
+    );
+}
+
+/// Check that a delay of `delay_sec` seconds is within bounds.
+pub fn assert_valid_delay(delay_sec: u64) {
+    require!(delay_sec <= MAX_DELAY_SEC, ERR_INVALID_DELAY);
+}
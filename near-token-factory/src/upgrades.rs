@@ -0,0 +1,185 @@
+use crate::{
+    address_from_token_account_id, ext, AclRole, Contract, ContractExt, ERR_TOKEN_NOT_REGISTERED,
+};
+use near_plugins::access_control_any;
+use near_sdk::{env, near_bindgen, require, AccountId, Gas, Promise, PromiseOrValue};
+use near_token_common as aurora_sdk;
+
+const UPGRADE_TOKEN_COST: Gas = Gas(30_000_000_000_000);
+const MIGRATE_TOKEN_COST: Gas = Gas(15_000_000_000_000);
+const ON_TOKEN_MIGRATED_COST: Gas = Gas(5_000_000_000_000);
+const ON_TOKEN_UPGRADED_COST: Gas =
+    Gas(5_000_000_000_000 + MIGRATE_TOKEN_COST.0 + ON_TOKEN_MIGRATED_COST.0);
+/// Gas of a whole token upgrade, including the migration and the callbacks.
+const LAZY_UPGRADE_COST: Gas = Gas(UPGRADE_TOKEN_COST.0 + ON_TOKEN_UPGRADED_COST.0);
+/// Gas kept for the rest of the call that schedules a lazy upgrade.
+const LAZY_UPGRADE_GAS_MARGIN: Gas = Gas(10_000_000_000_000);
+
+const ERR_LAZY_UPGRADES_DISABLED: &str =
+    "ERR_LAZY_UPGRADES_DISABLED: Lazy token upgrades are disabled.";
+const ERR_TOKEN_UP_TO_DATE: &str = "ERR_TOKEN_UP_TO_DATE: Token runs the active binary.";
+const ERR_UPGRADE_IN_PROGRESS: &str =
+    "ERR_UPGRADE_IN_PROGRESS: An upgrade of this token is already in progress.";
+
+#[near_bindgen]
+impl Contract {
+    /// Enable or disable lazy upgrades. When enabled, a token running an outdated
+    /// binary is upgraded when the factory calls it on a deposit or metadata update, or
+    /// when it calls `on_withdraw`, and anyone can upgrade it with `upgrade_token`,
+    /// instead of upgrading all tokens at once. ONLY accounts with [`AclRole::Admin`]
+    /// role can call this method.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn set_lazy_token_upgrades(&mut self, enabled: bool) {
+        self.lazy_token_upgrades = enabled;
+    }
+
+    /// Returns whether tokens are upgraded lazily.
+    pub fn lazy_token_upgrades(&self) -> bool {
+        self.lazy_token_upgrades
+    }
+
+    /// Upgrade `token_account_id` to the active binary. This is a public method, but
+    /// it fails if lazy upgrades are disabled, the token already runs the active binary
+    /// or an upgrade of the token is already in progress. It can be used to upgrade
+    /// tokens that are not used, or whose deposits and withdrawals didn't attach
+    /// enough gas to upgrade them.
+    ///
+    /// Once the binary is deployed, the factory calls `migrate` on the token with its
+    /// provenance and the new version, since tokens deployed with the first binary
+    /// don't migrate their state in `upgrade_contract`.
+    ///
+    /// The attached gas MUST cover `upgrade_contract` (30 TGas), `migrate` (15 TGas)
+    /// and the callbacks (10 TGas).
+    pub fn upgrade_token(&mut self, token_account_id: AccountId) -> Promise {
+        require!(self.lazy_token_upgrades, ERR_LAZY_UPGRADES_DISABLED);
+        let info = self
+            .tokens
+            .get(&token_account_id)
+            .unwrap_or_else(|| env::panic_str(ERR_TOKEN_NOT_REGISTERED));
+        require!(
+            info.version != self.token_binary_version,
+            ERR_TOKEN_UP_TO_DATE
+        );
+        require!(
+            self.upgrading_tokens.insert(&token_account_id),
+            ERR_UPGRADE_IN_PROGRESS
+        );
+        let binary = self.get_token_binary();
+
+        ext::ext_near_token::ext(token_account_id.clone())
+            .with_static_gas(UPGRADE_TOKEN_COST)
+            .upgrade_contract(binary.into())
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(ON_TOKEN_UPGRADED_COST)
+                    .on_token_upgraded(token_account_id, self.token_binary_version),
+            )
+    }
+
+    /// Returns whether an upgrade of `token_account_id` is in progress.
+    pub fn is_token_upgrading(&self, token_account_id: AccountId) -> bool {
+        self.upgrading_tokens.contains(&token_account_id)
+    }
+
+    /// Callback after upgrading a token. Migrates the state of the token if the upgrade
+    /// succeeded. Otherwise allows upgrading the token again and returns `false`.
+    #[private]
+    pub fn on_token_upgraded(
+        &mut self,
+        token_account_id: AccountId,
+        version: u32,
+    ) -> PromiseOrValue<bool> {
+        if !near_sdk::is_promise_success() {
+            self.upgrading_tokens.remove(&token_account_id);
+            return PromiseOrValue::Value(false);
+        }
+
+        let info = aurora_sdk::BridgedTokenInfo {
+            erc20_address: address_from_token_account_id(token_account_id.clone()),
+            aurora: self.aurora.clone(),
+            locker: self.locker.clone(),
+            factory: env::current_account_id(),
+            binary_version: version,
+        };
+        ext::ext_near_token::ext(token_account_id.clone())
+            .with_static_gas(MIGRATE_TOKEN_COST)
+            .migrate(Some(info))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(ON_TOKEN_MIGRATED_COST)
+                    .on_token_migrated(token_account_id, version),
+            )
+            .into()
+    }
+
+    /// Callback after migrating the state of an upgraded token. Records `version` as
+    /// the version of the token if the migration succeeded, and allows upgrading the
+    /// token again. Returns whether the upgrade succeeded.
+    #[private]
+    pub fn on_token_migrated(&mut self, token_account_id: AccountId, version: u32) -> bool {
+        self.upgrading_tokens.remove(&token_account_id);
+        if !near_sdk::is_promise_success() {
+            return false;
+        }
+
+        if let Some(mut info) = self.tokens.get(&token_account_id) {
+            info.version = version;
+            self.tokens.insert(&token_account_id, &info);
+        }
+        true
+    }
+}
+
+impl Contract {
+    /// Run `promise`, which calls `token_account_id` with `promise_gas`, after
+    /// upgrading the token to the active binary if [`Self::lazy_upgrade`] schedules
+    /// an upgrade. `promise` runs even if the upgrade fails.
+    pub(crate) fn with_lazy_upgrade(
+        &mut self,
+        token_account_id: &AccountId,
+        promise: Promise,
+        promise_gas: Gas,
+    ) -> Promise {
+        match self.lazy_upgrade(token_account_id, promise_gas) {
+            Some(upgrade) => upgrade.then(promise),
+            None => promise,
+        }
+    }
+
+    /// Upgrade `token_account_id` to the active binary if lazy upgrades are enabled,
+    /// the token runs an older binary, no upgrade of the token is in progress and the
+    /// gas left covers the upgrade on top of `reserved_gas`. Otherwise the token is
+    /// left as is, so the call that touched it isn't affected.
+    pub(crate) fn lazy_upgrade(
+        &mut self,
+        token_account_id: &AccountId,
+        reserved_gas: Gas,
+    ) -> Option<Promise> {
+        if !self.lazy_token_upgrades {
+            return None;
+        }
+        let info = self.tokens.get(token_account_id)?;
+        if info.version >= self.token_binary_version {
+            return None;
+        }
+        let remaining_gas = env::prepaid_gas().0.saturating_sub(env::used_gas().0);
+        if remaining_gas < LAZY_UPGRADE_COST.0 + reserved_gas.0 + LAZY_UPGRADE_GAS_MARGIN.0 {
+            return None;
+        }
+        let binary = self.token_binaries.get(&self.token_binary_version)?;
+        if !self.upgrading_tokens.insert(token_account_id) {
+            return None;
+        }
+
+        Some(
+            ext::ext_near_token::ext(token_account_id.clone())
+                .with_static_gas(UPGRADE_TOKEN_COST)
+                .upgrade_contract(binary.into())
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(ON_TOKEN_UPGRADED_COST)
+                        .on_token_upgraded(token_account_id.clone(), self.token_binary_version),
+                ),
+        )
+    }
+}
@@ -0,0 +1,187 @@
+//! Minimal parser of WASM binaries, used to validate token binaries before accepting
+//! them. Only the export section is decoded.
+
+const WASM_MAGIC: [u8; 4] = [0x00, 0x61, 0x73, 0x6d];
+const WASM_VERSION: [u8; 4] = [0x01, 0x00, 0x00, 0x00];
+const EXPORT_SECTION_ID: u8 = 7;
+const FUNCTION_EXPORT_KIND: u8 = 0;
+
+/// Methods that every token binary MUST export.
+pub const REQUIRED_TOKEN_EXPORTS: [&str; 15] = [
+    "new",
+    "deposit",
+    "deposit_call",
+    "withdraw",
+    "upgrade_contract",
+    "migrate",
+    "self_destruct",
+    "update_metadata",
+    "ft_transfer",
+    "ft_transfer_call",
+    "ft_total_supply",
+    "ft_balance_of",
+    "ft_resolve_transfer",
+    "ft_metadata",
+    "storage_deposit",
+];
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn read_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.data.len() {
+            return None;
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(bytes)
+    }
+
+    fn read_u8(&mut self) -> Option<u8> {
+        self.read_bytes(1).map(|bytes| bytes[0])
+    }
+
+    /// Read an unsigned LEB128 encoded `u32`. Encodings longer than 5 bytes, or whose
+    /// last byte sets bits beyond the 32nd, are rejected.
+    fn read_u32(&mut self) -> Option<u32> {
+        let mut result = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.read_u8()?;
+            if shift == 28 && byte > 0x0f {
+                return None;
+            }
+            result |= u32::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Some(result);
+            }
+        }
+        None
+    }
+}
+
+/// Returns the names of the functions exported by `binary`, or `None` if it is not a
+/// valid WASM module.
+pub fn exported_functions(binary: &[u8]) -> Option<Vec<String>> {
+    let mut reader = Reader { data: binary };
+    if reader.read_bytes(4)? != WASM_MAGIC || reader.read_bytes(4)? != WASM_VERSION {
+        return None;
+    }
+
+    while !reader.data.is_empty() {
+        let id = reader.read_u8()?;
+        let size = reader.read_u32()? as usize;
+        let section = reader.read_bytes(size)?;
+        if id != EXPORT_SECTION_ID {
+            continue;
+        }
+
+        let mut section = Reader { data: section };
+        let count = section.read_u32()?;
+        let mut exports = Vec::new();
+        for _ in 0..count {
+            let len = section.read_u32()? as usize;
+            let name = std::str::from_utf8(section.read_bytes(len)?).ok()?;
+            let kind = section.read_u8()?;
+            section.read_u32()?;
+            if kind == FUNCTION_EXPORT_KIND {
+                exports.push(name.to_string());
+            }
+        }
+        return Some(exports);
+    }
+
+    Some(Vec::new())
+}
+
+/// Returns the required token methods that are not exported by `binary`, or `None` if
+/// it is not a valid WASM module.
+pub fn missing_token_exports(binary: &[u8]) -> Option<Vec<String>> {
+    let exports = exported_functions(binary)?;
+    Some(
+        REQUIRED_TOKEN_EXPORTS
+            .iter()
+            .filter(|method| !exports.iter().any(|export| export == *method))
+            .map(|method| method.to_string())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{exported_functions, missing_token_exports, REQUIRED_TOKEN_EXPORTS};
+
+    /// Unsigned LEB128 encoding of `value`.
+    fn leb128(mut value: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                bytes.push(byte);
+                return bytes;
+            }
+            bytes.push(byte | 0x80);
+        }
+    }
+
+    /// Build a WASM module with a type section and an export section exporting
+    /// `functions` as functions and `memory` as memory.
+    fn module(functions: &[&str]) -> Vec<u8> {
+        let mut exports = leb128(functions.len() + 1);
+        for function in functions {
+            exports.push(function.len() as u8);
+            exports.extend_from_slice(function.as_bytes());
+            exports.extend_from_slice(&[0x00, 0x00]);
+        }
+        exports.extend_from_slice(&[6, b'm', b'e', b'm', b'o', b'r', b'y', 0x02, 0x00]);
+
+        let mut binary = vec![0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+        // Type section with a single `() -> ()` function type.
+        binary.extend_from_slice(&[0x01, 0x04, 0x01, 0x60, 0x00, 0x00]);
+        binary.push(0x07);
+        binary.extend_from_slice(&leb128(exports.len()));
+        binary.extend_from_slice(&exports);
+        binary
+    }
+
+    #[test]
+    fn test_exported_functions() {
+        assert_eq!(
+            exported_functions(&module(&["new", "withdraw"])).unwrap(),
+            vec!["new".to_string(), "withdraw".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_missing_token_exports() {
+        assert_eq!(
+            missing_token_exports(&module(&REQUIRED_TOKEN_EXPORTS)).unwrap(),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            missing_token_exports(&module(&REQUIRED_TOKEN_EXPORTS[1..])).unwrap(),
+            vec!["new".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_read_u32() {
+        let read = |data: &[u8]| super::Reader { data }.read_u32();
+        assert_eq!(read(&[0xe5, 0x8e, 0x26]), Some(624_485));
+        assert_eq!(read(&[0xff, 0xff, 0xff, 0xff, 0x0f]), Some(u32::MAX));
+        assert_eq!(read(&[0xff, 0xff, 0xff, 0xff, 0x1f]), None);
+        assert_eq!(read(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x00]), None);
+        assert_eq!(read(&[0x80]), None);
+    }
+
+    #[test]
+    fn test_invalid_wasm() {
+        assert!(exported_functions(b"not wasm").is_none());
+
+        let mut binary = module(&["new"]);
+        binary.truncate(binary.len() - 1);
+        assert!(exported_functions(&binary).is_none());
+    }
+}
@@ -0,0 +1,317 @@
+use crate::events::{WithdrawalBatchFailed, WithdrawalSent};
+use crate::pause::Direction;
+use crate::time::{self, NANOS_PER_SECOND};
+use crate::withdrawals::ERR_WITHDRAW_PAUSED;
+use crate::{
+    abi_decode_bool_array, abi_encode_withdraw_batch, account_id_from_token_address,
+    address_from_token_account_id, AclRole, Contract, ContractExt,
+};
+use near_plugins::{access_control_any, AccessControllable};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, require, AccountId, Gas, Promise, PromiseResult};
+use near_token_common as aurora_sdk;
+
+/// Gas limit of a function call.
+const MAX_PREPAID_GAS: Gas = Gas(300_000_000_000_000);
+// TODO: Determine properly what are good gas constants for these steps.
+/// Gas used by `flush_withdrawals` itself, excluding the calls it schedules.
+const FLUSH_WITHDRAWALS_COST: Gas = Gas(20_000_000_000_000);
+/// Gas of the locker call for a batch, excluding the withdrawals in it.
+const WITHDRAW_BATCH_BASE_COST: Gas = Gas(30_000_000_000_000);
+/// Gas of the locker call for each withdrawal in a batch.
+const WITHDRAW_BATCH_ITEM_COST: Gas = Gas(5_000_000_000_000);
+/// Gas of the callback of a batch, excluding the withdrawals in it.
+const ON_WITHDRAWAL_BATCH_SENT_BASE_COST: Gas = Gas(10_000_000_000_000);
+/// Gas of the callback for each withdrawal in a batch, which is held if it failed.
+const ON_WITHDRAWAL_BATCH_SENT_ITEM_COST: Gas = Gas(1_000_000_000_000);
+/// Gas kept for the rest of the call that flushes a full batch automatically.
+const AUTO_FLUSH_GAS_MARGIN: Gas = Gas(10_000_000_000_000);
+
+/// Largest batch whose flush fits in the gas limit of a function call.
+const MAX_WITHDRAWAL_BATCH_SIZE: u32 = ((MAX_PREPAID_GAS.0
+    - FLUSH_WITHDRAWALS_COST.0
+    - WITHDRAW_BATCH_BASE_COST.0
+    - ON_WITHDRAWAL_BATCH_SENT_BASE_COST.0)
+    / (WITHDRAW_BATCH_ITEM_COST.0 + ON_WITHDRAWAL_BATCH_SENT_ITEM_COST.0))
+    as u32;
+
+const ERR_INVALID_BATCH_SIZE: &str =
+    "ERR_INVALID_BATCH_SIZE: Batch size must be positive and fit in the gas of a flush.";
+const ERR_EMPTY_BATCH: &str = "ERR_EMPTY_BATCH: No withdrawals to flush for this token.";
+const ERR_BATCH_NOT_READY: &str =
+    "ERR_BATCH_NOT_READY: Batch didn't reach a threshold, only an admin can flush it.";
+
+/// A batch of withdrawals is flushed as soon as it holds `max_size` withdrawals, and
+/// can be flushed by anyone once its first withdrawal is `max_delay_sec` seconds old.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct WithdrawalBatchConfig {
+    pub max_size: u32,
+    pub max_delay_sec: u64,
+}
+
+/// Withdrawal whose tokens are already burnt and whose fee is already charged,
+/// waiting in a batch to be sent to the locker.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BatchedWithdrawal {
+    /// Account on NEAR whose tokens were burnt. `None` if the token doesn't report
+    /// the sender of withdrawals.
+    pub sender_id: Option<AccountId>,
+    /// Address on Aurora that receives the tokens.
+    pub receiver_id: aurora_sdk::Address,
+    /// Amount unlocked by the locker, net of the fee.
+    pub amount: U128,
+    pub transfer_id: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct WithdrawalBatch {
+    pub withdrawals: Vec<BatchedWithdrawal>,
+    /// Block timestamp in nanoseconds when the first withdrawal was added.
+    pub started_at: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Set the thresholds for flushing batched withdrawals, or send withdrawals one by
+    /// one if `config` is `None`. Withdrawals that are already batched are still sent
+    /// by [`flush_withdrawals`](Contract::flush_withdrawals). The size can't exceed
+    /// `MAX_WITHDRAWAL_BATCH_SIZE`. ONLY accounts with [`AclRole::Admin`] role can
+    /// call this method.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn set_withdrawal_batching(&mut self, config: Option<WithdrawalBatchConfig>) {
+        if let Some(config) = &config {
+            require!(
+                (1..=MAX_WITHDRAWAL_BATCH_SIZE).contains(&config.max_size),
+                ERR_INVALID_BATCH_SIZE
+            );
+            time::assert_valid_delay(config.max_delay_sec);
+        }
+        self.withdrawal_batching = config;
+    }
+
+    /// Returns the thresholds for flushing batched withdrawals.
+    pub fn get_withdrawal_batching(&self) -> Option<WithdrawalBatchConfig> {
+        self.withdrawal_batching.clone()
+    }
+
+    /// Returns the withdrawals of `token` waiting to be flushed, if any.
+    pub fn get_withdrawal_batch(&self, token: aurora_sdk::Address) -> Option<WithdrawalBatch> {
+        self.withdrawal_batches
+            .get(&account_id_from_token_address(token))
+    }
+
+    /// Send the oldest batched withdrawals of `token` to the locker in a single call,
+    /// up to `MAX_WITHDRAWAL_BATCH_SIZE` of them. A batch is flushed automatically by
+    /// the withdrawal that fills it, if that call has enough gas left. Otherwise, and
+    /// for batches that don't fill up, this method sends it.
+    ///
+    /// This is a public method once the batch is full or its first withdrawal is
+    /// `max_delay_sec` seconds old, since the receivers are fixed. Before that, ONLY
+    /// accounts with [`AclRole::Admin`] role can flush it. If batching is disabled,
+    /// anyone can flush the remaining withdrawals. Fails if withdrawals are paused for
+    /// the token, including by its circuit breaker.
+    ///
+    /// Withdrawals that the locker fails to unlock are held as pending withdrawals.
+    ///
+    /// The attached gas MUST cover the locker call, i.e. 30 TGas plus 5 TGas per
+    /// withdrawal, and the callback, i.e. 10 TGas plus 1 TGas per withdrawal.
+    pub fn flush_withdrawals(&mut self, token: aurora_sdk::Address) -> Promise {
+        let token_account_id = account_id_from_token_address(token);
+        require!(
+            !self.is_paused(&token_account_id, Direction::Withdraw),
+            ERR_WITHDRAW_PAUSED
+        );
+        let batch = self
+            .withdrawal_batches
+            .remove(&token_account_id)
+            .unwrap_or_else(|| env::panic_str(ERR_EMPTY_BATCH));
+        require!(
+            self.is_batch_ready(&batch)
+                || self.acl_has_role(AclRole::Admin.into(), env::predecessor_account_id()),
+            ERR_BATCH_NOT_READY
+        );
+        self.flush_batch(&token_account_id, batch)
+    }
+
+    /// Callback after the locker call of a batch of withdrawals. The locker tries each
+    /// transfer on its own and returns whether it succeeded. Withdrawals that failed
+    /// are held as pending withdrawals, so they can be executed again or cancelled by
+    /// a guardian, without blocking the next batches. If the whole call failed or its
+    /// result can't be decoded, every withdrawal in the batch is held. Returns whether
+    /// each withdrawal was unlocked.
+    ///
+    /// Emit `withdrawal_batch_failed` event if any withdrawal failed, and
+    /// `withdrawal_held` event for each of them.
+    #[private]
+    pub fn on_withdrawal_batch_sent(
+        &mut self,
+        token_account_id: AccountId,
+        withdrawals: Vec<BatchedWithdrawal>,
+    ) -> Vec<bool> {
+        let results = match env::promise_result(0) {
+            PromiseResult::Successful(output) => {
+                match aurora_sdk::SubmitResult::try_from_slice(&output) {
+                    Ok(aurora_sdk::SubmitResult {
+                        status: aurora_sdk::TransactionStatus::Succeed(output),
+                        ..
+                    }) => abi_decode_bool_array(&output),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+        .filter(|results| results.len() == withdrawals.len())
+        .unwrap_or_else(|| vec![false; withdrawals.len()]);
+
+        let failed: Vec<_> = withdrawals
+            .into_iter()
+            .zip(&results)
+            .filter(|(_, &unlocked)| !unlocked)
+            .map(|(withdrawal, _)| withdrawal)
+            .collect();
+        if failed.is_empty() {
+            return results;
+        }
+
+        WithdrawalBatchFailed {
+            token_id: &token_account_id,
+            transfer_ids: failed.iter().map(|w| w.transfer_id).collect(),
+        }
+        .emit();
+
+        for withdrawal in failed {
+            self.hold_withdrawal(
+                token_account_id.clone(),
+                withdrawal.sender_id,
+                withdrawal.receiver_id,
+                withdrawal.amount,
+                None,
+                withdrawal.transfer_id,
+            );
+        }
+        results
+    }
+}
+
+impl Contract {
+    /// Add a withdrawal to the batch of `token_account_id`. If the batch is full and
+    /// the gas left covers its flush, the batch is sent right away and the promise is
+    /// returned. Otherwise it waits for
+    /// [`flush_withdrawals`](Contract::flush_withdrawals).
+    pub(crate) fn batch_withdrawal(
+        &mut self,
+        token_account_id: &AccountId,
+        sender_id: Option<AccountId>,
+        receiver_id: &aurora_sdk::Address,
+        amount: u128,
+        transfer_id: u64,
+    ) -> Option<Promise> {
+        let mut batch = self
+            .withdrawal_batches
+            .get(token_account_id)
+            .unwrap_or(WithdrawalBatch {
+                withdrawals: Vec::new(),
+                started_at: env::block_timestamp(),
+            });
+        batch.withdrawals.push(BatchedWithdrawal {
+            sender_id,
+            receiver_id: receiver_id.clone(),
+            amount: amount.into(),
+            transfer_id,
+        });
+        self.add_outstanding_withdrawal(token_account_id, amount);
+
+        let is_full = self.withdrawal_batching.as_ref().map_or(true, |config| {
+            batch.withdrawals.len() >= config.max_size as usize
+        });
+        let remaining_gas = env::prepaid_gas().0.saturating_sub(env::used_gas().0);
+        let flush_gas = send_withdrawal_batch_cost(
+            batch
+                .withdrawals
+                .len()
+                .min(MAX_WITHDRAWAL_BATCH_SIZE as usize),
+        );
+        if is_full && remaining_gas >= flush_gas.0 + AUTO_FLUSH_GAS_MARGIN.0 {
+            return Some(self.flush_batch(token_account_id, batch));
+        }
+
+        self.withdrawal_batches.insert(token_account_id, &batch);
+        None
+    }
+
+    /// Send up to `MAX_WITHDRAWAL_BATCH_SIZE` of the oldest withdrawals in `batch` and
+    /// store the rest, which keeps its start time, so it can be flushed right away.
+    fn flush_batch(&mut self, token_account_id: &AccountId, mut batch: WithdrawalBatch) -> Promise {
+        let size = batch
+            .withdrawals
+            .len()
+            .min(MAX_WITHDRAWAL_BATCH_SIZE as usize);
+        let withdrawals: Vec<_> = batch.withdrawals.drain(..size).collect();
+        if !batch.withdrawals.is_empty() {
+            self.withdrawal_batches.insert(token_account_id, &batch);
+        }
+        self.send_withdrawal_batch(token_account_id, withdrawals)
+    }
+
+    /// Returns whether `batch` reached the size or time threshold. Batches are always
+    /// ready if batching is disabled.
+    fn is_batch_ready(&self, batch: &WithdrawalBatch) -> bool {
+        match &self.withdrawal_batching {
+            Some(config) => {
+                batch.withdrawals.len() >= config.max_size as usize
+                    || env::block_timestamp()
+                        >= batch.started_at + config.max_delay_sec * NANOS_PER_SECOND
+            }
+            None => true,
+        }
+    }
+
+    /// Call the locker in Aurora to unlock all `withdrawals`.
+    ///
+    /// Emit `withdrawal_sent` event for each withdrawal.
+    fn send_withdrawal_batch(
+        &mut self,
+        token_account_id: &AccountId,
+        withdrawals: Vec<BatchedWithdrawal>,
+    ) -> Promise {
+        for withdrawal in &withdrawals {
+            self.remove_outstanding_withdrawal(token_account_id, withdrawal.amount.into());
+            WithdrawalSent {
+                transfer_id: withdrawal.transfer_id,
+                token_id: token_account_id,
+                receiver_id: &withdrawal.receiver_id,
+                amount: withdrawal.amount,
+            }
+            .emit();
+        }
+
+        let token_id = address_from_token_account_id(token_account_id.clone());
+        let input = abi_encode_withdraw_batch(&token_id, &withdrawals);
+        let size = withdrawals.len() as u64;
+
+        aurora_sdk::aurora::ext_aurora::ext(self.aurora.clone())
+            .with_static_gas(Gas(
+                WITHDRAW_BATCH_BASE_COST.0 + WITHDRAW_BATCH_ITEM_COST.0 * size
+            ))
+            .call(aurora_sdk::aurora::call_args(self.locker.clone(), input))
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas(ON_WITHDRAWAL_BATCH_SENT_BASE_COST.0
+                        + ON_WITHDRAWAL_BATCH_SENT_ITEM_COST.0 * size))
+                    .on_withdrawal_batch_sent(token_account_id.clone(), withdrawals),
+            )
+    }
+}
+
+/// Gas attached by `send_withdrawal_batch` for a batch of `size` withdrawals.
+fn send_withdrawal_batch_cost(size: usize) -> Gas {
+    let size = size as u64;
+    Gas(WITHDRAW_BATCH_BASE_COST.0
+        + ON_WITHDRAWAL_BATCH_SENT_BASE_COST.0
+        + (WITHDRAW_BATCH_ITEM_COST.0 + ON_WITHDRAWAL_BATCH_SENT_ITEM_COST.0) * size)
+}
@@ -0,0 +1,275 @@
+use crate::events::{WithdrawalCancelled, WithdrawalHeld};
+use crate::pause::Direction;
+use crate::rate_limit::ERR_RATE_LIMIT_EXCEEDED;
+use crate::time::{self, NANOS_PER_SECOND};
+use crate::{account_id_from_token_address, ext, AclRole, Contract, ContractExt, DEPOSIT_COST};
+use near_plugins::access_control_any;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, require, AccountId, Gas, Promise, PromiseOrValue};
+use near_token_common as aurora_sdk;
+
+const ON_WITHDRAWAL_CANCELLED_COST: Gas = Gas(5_000_000_000_000);
+
+const ERR_WITHDRAWAL_NOT_FOUND: &str = "ERR_WITHDRAWAL_NOT_FOUND: Withdrawal doesn't exist.";
+pub(crate) const ERR_WITHDRAW_PAUSED: &str =
+    "ERR_WITHDRAW_PAUSED: Withdrawals are paused for this token.";
+const ERR_WITHDRAWAL_LOCKED: &str = "ERR_WITHDRAWAL_LOCKED: Withdrawal is still time-locked.";
+const ERR_UNKNOWN_SENDER: &str =
+    "ERR_UNKNOWN_SENDER: Withdrawal can't be cancelled, its token didn't report the sender.";
+
+/// Withdrawal whose tokens are already burnt on NEAR, but which is held by the
+/// factory instead of being sent to the locker.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingWithdrawal {
+    /// Account id of the NEP-141 token.
+    pub token_id: AccountId,
+    /// Account on NEAR whose tokens were burnt. `None` if the token doesn't report
+    /// the sender of withdrawals.
+    pub sender_id: Option<AccountId>,
+    /// Address on Aurora that receives the tokens.
+    pub receiver_id: aurora_sdk::Address,
+    pub amount: U128,
+    /// Block timestamp in nanoseconds when the withdrawal was held.
+    pub created_at: u64,
+    /// Block timestamp in nanoseconds before which a large withdrawal can't be
+    /// executed. `None` for withdrawals that are not time-locked.
+    pub unlock_at: Option<u64>,
+    /// Id of the transfer, sent to the locker when the withdrawal is executed.
+    pub transfer_id: u64,
+}
+
+/// Withdrawals of at least `threshold` tokens are time-locked for `delay_sec`
+/// seconds before they can be sent to the locker.
+#[derive(BorshDeserialize, BorshSerialize, Deserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LargeWithdrawalConfig {
+    pub threshold: U128,
+    pub delay_sec: u64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Send a pending withdrawal to the locker. This is a public method with no
+    /// access control, since the receiver is fixed. It fails if the withdrawal is
+    /// still time-locked, withdrawals are still paused for the token or the amount
+    /// exceeds its rate limit.
+    pub fn execute_pending_withdrawal(&mut self, withdrawal_id: u64) -> PromiseOrValue<()> {
+        let withdrawal = self.get_pending_withdrawal_or_panic(withdrawal_id);
+        require!(
+            withdrawal
+                .unlock_at
+                .map_or(true, |unlock_at| env::block_timestamp() >= unlock_at),
+            ERR_WITHDRAWAL_LOCKED
+        );
+        require!(
+            !self.is_paused(&withdrawal.token_id, Direction::Withdraw),
+            ERR_WITHDRAW_PAUSED
+        );
+        require!(
+            self.try_consume_rate_limit(
+                &withdrawal.token_id,
+                Direction::Withdraw,
+                withdrawal.amount.into()
+            ),
+            ERR_RATE_LIMIT_EXCEEDED
+        );
+
+        self.pending_withdrawals.remove(&withdrawal_id);
+        self.remove_outstanding_withdrawal(&withdrawal.token_id, withdrawal.amount.into());
+        self.send_withdrawal(
+            &withdrawal.token_id,
+            withdrawal.sender_id,
+            &withdrawal.receiver_id,
+            withdrawal.amount.into(),
+            withdrawal.transfer_id,
+        )
+    }
+
+    /// Cancel a pending withdrawal and mint the tokens back to the sender on NEAR.
+    /// If minting fails, the withdrawal is restored. ONLY accounts with
+    /// [`AclRole::Guardian`] role can call this method.
+    ///
+    /// Emit `withdrawal_cancelled` event once the tokens are minted back.
+    #[access_control_any(roles(AclRole::Guardian))]
+    pub fn cancel_pending_withdrawal(&mut self, withdrawal_id: u64) -> Promise {
+        let withdrawal = self.get_pending_withdrawal_or_panic(withdrawal_id);
+        let sender_id = withdrawal
+            .sender_id
+            .clone()
+            .unwrap_or_else(|| env::panic_str(ERR_UNKNOWN_SENDER));
+        self.pending_withdrawals.remove(&withdrawal_id);
+        self.remove_outstanding_withdrawal(&withdrawal.token_id, withdrawal.amount.into());
+
+        ext::ext_near_token::ext(withdrawal.token_id.clone())
+            .with_static_gas(DEPOSIT_COST)
+            .remint(
+                sender_id,
+                withdrawal.amount,
+                Some("Cancelled withdrawal".to_string()),
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(ON_WITHDRAWAL_CANCELLED_COST)
+                    .on_withdrawal_cancelled(
+                        withdrawal_id,
+                        withdrawal,
+                        env::predecessor_account_id(),
+                    ),
+            )
+    }
+
+    /// Callback after the tokens of a cancelled withdrawal are minted back. Restores
+    /// the withdrawal if minting failed. Returns whether the withdrawal was cancelled.
+    ///
+    /// Emit `withdrawal_cancelled` event if the tokens were minted back.
+    #[private]
+    pub fn on_withdrawal_cancelled(
+        &mut self,
+        withdrawal_id: u64,
+        withdrawal: PendingWithdrawal,
+        cancelled_by: AccountId,
+    ) -> bool {
+        if !near_sdk::is_promise_success() {
+            self.add_outstanding_withdrawal(&withdrawal.token_id, withdrawal.amount.into());
+            self.pending_withdrawals.insert(&withdrawal_id, &withdrawal);
+            return false;
+        }
+
+        WithdrawalCancelled {
+            withdrawal_id,
+            token_id: &withdrawal.token_id,
+            sender_id: withdrawal.sender_id.as_ref().unwrap(),
+            amount: withdrawal.amount,
+            cancelled_by: &cancelled_by,
+        }
+        .emit();
+        true
+    }
+
+    /// Set the threshold and delay for time-locking large withdrawals of `token`, or
+    /// remove them if `config` is `None`. ONLY accounts with [`AclRole::Admin`] role
+    /// can call this method.
+    #[access_control_any(roles(AclRole::Admin))]
+    pub fn set_large_withdrawal_config(
+        &mut self,
+        token: aurora_sdk::Address,
+        config: Option<LargeWithdrawalConfig>,
+    ) {
+        let token_account_id = account_id_from_token_address(token);
+        match config {
+            Some(config) => {
+                time::assert_valid_delay(config.delay_sec);
+                self.large_withdrawal_configs
+                    .insert(&token_account_id, &config);
+            }
+            None => {
+                self.large_withdrawal_configs.remove(&token_account_id);
+            }
+        }
+    }
+
+    /// Returns the threshold and delay for time-locking large withdrawals of `token`.
+    pub fn get_large_withdrawal_config(
+        &self,
+        token: aurora_sdk::Address,
+    ) -> Option<LargeWithdrawalConfig> {
+        self.large_withdrawal_configs
+            .get(&account_id_from_token_address(token))
+    }
+
+    /// Returns the pending withdrawal with id `withdrawal_id`, if any.
+    pub fn get_pending_withdrawal(&self, withdrawal_id: u64) -> Option<PendingWithdrawal> {
+        self.pending_withdrawals.get(&withdrawal_id)
+    }
+
+    /// Returns up to `limit` pending withdrawals and their ids, after skipping the
+    /// first `skip` ones.
+    pub fn get_pending_withdrawals(&self, skip: u64, limit: u64) -> Vec<(u64, PendingWithdrawal)> {
+        self.pending_withdrawals
+            .iter()
+            .skip(skip as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    /// Returns up to `limit` time-locked pending withdrawals and their ids, after
+    /// skipping the first `skip` ones.
+    pub fn get_pending_large_withdrawals(
+        &self,
+        skip: u64,
+        limit: u64,
+    ) -> Vec<(u64, PendingWithdrawal)> {
+        self.pending_withdrawals
+            .iter()
+            .filter(|(_, withdrawal)| withdrawal.unlock_at.is_some())
+            .skip(skip as usize)
+            .take(limit as usize)
+            .collect()
+    }
+}
+
+impl Contract {
+    /// Returns the time at which a withdrawal of `amount` tokens can be executed, if
+    /// it must be time-locked.
+    pub(crate) fn large_withdrawal_unlock_at(
+        &self,
+        token_id: &AccountId,
+        amount: U128,
+    ) -> Option<u64> {
+        let config = self.large_withdrawal_configs.get(token_id)?;
+        if amount.0 < config.threshold.0 {
+            return None;
+        }
+        Some(env::block_timestamp() + config.delay_sec * NANOS_PER_SECOND)
+    }
+
+    /// Store a withdrawal to be executed later. Emit `withdrawal_held` event.
+    pub(crate) fn hold_withdrawal(
+        &mut self,
+        token_id: AccountId,
+        sender_id: Option<AccountId>,
+        receiver_id: aurora_sdk::Address,
+        amount: U128,
+        unlock_at: Option<u64>,
+        transfer_id: u64,
+    ) -> u64 {
+        let withdrawal_id = self.next_withdrawal_id;
+        self.next_withdrawal_id += 1;
+
+        WithdrawalHeld {
+            withdrawal_id,
+            token_id: &token_id,
+            sender_id: sender_id.as_ref(),
+            receiver_id: &receiver_id,
+            amount,
+            unlock_at,
+            transfer_id,
+        }
+        .emit();
+
+        self.add_outstanding_withdrawal(&token_id, amount.into());
+        self.pending_withdrawals.insert(
+            &withdrawal_id,
+            &PendingWithdrawal {
+                token_id,
+                sender_id,
+                receiver_id,
+                amount,
+                created_at: env::block_timestamp(),
+                unlock_at,
+                transfer_id,
+            },
+        );
+
+        withdrawal_id
+    }
+
+    fn get_pending_withdrawal_or_panic(&self, withdrawal_id: u64) -> PendingWithdrawal {
+        self.pending_withdrawals
+            .get(&withdrawal_id)
+            .unwrap_or_else(|| env::panic_str(ERR_WITHDRAWAL_NOT_FOUND))
+    }
+}
@@ -194,6 +194,39 @@ impl AuroraLocker {
         ContractInput(data)
     }
 
+    /// Deposit many tokens at once, given as (token, recipient, amount).
+    pub fn deposit_batch(
+        &self,
+        deposits: &[(Address, &workspaces::AccountId, u128)],
+    ) -> ContractInput {
+        let data = self
+            .abi
+            .function("depositBatch")
+            .unwrap()
+            .encode_input(&[
+                ethabi::Token::Array(
+                    deposits
+                        .iter()
+                        .map(|(token, _, _)| ethabi::Token::Address(token.raw()))
+                        .collect(),
+                ),
+                ethabi::Token::Array(
+                    deposits
+                        .iter()
+                        .map(|(_, recipient, _)| ethabi::Token::String(recipient.as_str().into()))
+                        .collect(),
+                ),
+                ethabi::Token::Array(
+                    deposits
+                        .iter()
+                        .map(|(_, _, amount)| ethabi::Token::Uint((*amount).into()))
+                        .collect(),
+                ),
+            ])
+            .unwrap();
+        ContractInput(data)
+    }
+
     pub fn init_near_account(&self) -> ContractInput {
         let data = self
             .abi
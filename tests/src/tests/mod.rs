@@ -158,6 +158,133 @@ async fn test_deploy_token_factory() {
         .unwrap();
 }
 
+#[tokio::test]
+async fn test_token_factory_pause_token() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let engine = aurora_engine_utils::deploy_latest(&worker).await?;
+    let locker_address = Address::decode("000000000000000000000000000000000000000a").unwrap();
+    let factory = TokenFactory::deploy(&worker, locker_address, engine.inner.id()).await?;
+    let token = "000000000000000000000000000000000000000b";
+
+    // Only pause managers can pause tokens.
+    call_access_controlled_method(
+        factory.inner.as_account(),
+        &factory.inner,
+        "pause_token",
+        json!({ "token": token, "direction": "Withdraw" }),
+    )
+    .await?
+    .assert_acl_failure();
+
+    factory
+        .inner
+        .call("acl_grant_role")
+        .args_json(json!({ "role": "PauseManager", "account_id": factory.inner.id() }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    call_access_controlled_method(
+        factory.inner.as_account(),
+        &factory.inner,
+        "pause_token",
+        json!({ "token": token, "direction": "Withdraw" }),
+    )
+    .await?
+    .assert_success_unit_return();
+
+    let paused: serde_json::Value = factory
+        .inner
+        .view(
+            "token_paused",
+            json!({ "token": token }).to_string().into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(paused, json!({ "deposit": false, "withdraw": true }));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_token_factory_chunked_binary_upload() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let engine = aurora_engine_utils::deploy_latest(&worker).await?;
+    let locker_address = Address::decode("000000000000000000000000000000000000000a").unwrap();
+    let factory = TokenFactory::deploy(&worker, locker_address, engine.inner.id()).await?;
+    let wasm = TokenFactory::compile_token().await?;
+
+    factory
+        .inner
+        .call("acl_grant_role")
+        .args_json(json!({ "role": "Admin", "account_id": factory.inner.id() }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    // The same binary was uploaded as version 1 when deploying the factory.
+    let history: serde_json::Value = factory
+        .inner
+        .view(
+            "get_token_binary_history",
+            json!({ "skip": 0, "limit": 10 }).to_string().into_bytes(),
+        )
+        .await?
+        .json()?;
+    let expected_hash = history[0]["hash"].as_str().unwrap().to_string();
+
+    factory
+        .inner
+        .call("begin_binary_upload")
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    for chunk in wasm.chunks(wasm.len() / 3 + 1) {
+        factory
+            .inner
+            .call("append_binary_chunk")
+            .args_json(json!({ "chunk": base64::encode(chunk) }))
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+    }
+
+    // Finalizing fails if the hash doesn't match.
+    let result = factory
+        .inner
+        .call("finalize_binary_upload")
+        .args_json(json!({ "expected_hash": "00".repeat(32) }))
+        .max_gas()
+        .transact()
+        .await?;
+    let err = result.into_result().unwrap_err();
+    assert!(format!("{:?}", err).contains("ERR_BINARY_HASH_MISMATCH"));
+
+    let version: u32 = factory
+        .inner
+        .call("finalize_binary_upload")
+        .args_json(json!({ "expected_hash": expected_hash }))
+        .max_gas()
+        .transact()
+        .await?
+        .json()?;
+    assert_eq!(version, 2);
+
+    let version: u32 = factory
+        .inner
+        .call("activate_token_binary")
+        .max_gas()
+        .transact()
+        .await?
+        .json()?;
+    assert_eq!(version, 2);
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_near_token_contract_acl() -> anyhow::Result<()> {
     // Spin up a sandbox, compile, and deploy `near-token-contract`.
@@ -168,72 +295,912 @@ async fn test_near_token_contract_acl() -> anyhow::Result<()> {
     // Initialize the contract.
     contract
         .call("new")
-        .args_json(json!({
-            "super_admin": Some("token_admin"),
-        }))
+        .args_json(token_new_args(Some("token_admin")))
+        .deposit(near_sdk::ONE_NEAR)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Calling access controlled method from account without role fails.
+    let account_no_roles = worker.dev_create_account().await?;
+    call_access_controlled_method(
+        &account_no_roles,
+        &contract,
+        "update_metadata",
+        json!({ "metadata": UpdateFungibleTokenMetadata::default() }),
+    )
+    .await?
+    .assert_acl_failure();
+
+    // Calling access controlled method from account with permission succeeds.
+    call_access_controlled_method(
+        contract.as_account(),
+        &contract,
+        "update_metadata",
+        json!({ "metadata": UpdateFungibleTokenMetadata::default() }),
+    )
+    .await?
+    .assert_success_unit_return();
+
+    // Calling a method provided by `#[access_controllable]`.
+    assert!(!call_acl_has_role(&contract, "MetadataUpdater", account_no_roles.id()).await?);
+    assert!(call_acl_has_role(&contract, "MetadataUpdater", contract.id()).await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_near_token_contract_metadata_updaters() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let wasm = TokenFactory::compile_token().await?;
+    let contract = worker.dev_deploy(&wasm).await?;
+    let issuer = worker.dev_create_account().await?;
+
+    let mut args = token_new_args(None);
+    args["metadata_updaters"] = json!([issuer.id()]);
+    contract
+        .call("new")
+        .args_json(args)
+        .deposit(near_sdk::ONE_NEAR)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Accounts delegated by the issuer can update the metadata.
+    assert!(call_acl_has_role(&contract, "MetadataUpdater", issuer.id()).await?);
+    call_access_controlled_method(
+        &issuer,
+        &contract,
+        "update_metadata",
+        json!({ "metadata": UpdateFungibleTokenMetadata::default() }),
+    )
+    .await?
+    .assert_success_unit_return();
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_near_token_contract_decimals_frozen() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let wasm = TokenFactory::compile_token().await?;
+    let contract = worker.dev_deploy(&wasm).await?;
+
+    // Initialize the contract, making the contract itself factory and super-admin.
+    contract
+        .call("new")
+        .args_json(token_new_args(None))
+        .deposit(near_sdk::ONE_NEAR)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    // The first update setting decimals succeeds and freezes them.
+    let metadata = UpdateFungibleTokenMetadata {
+        decimals: Some(18),
+        ..Default::default()
+    };
+    contract
+        .call("update_metadata")
+        .args_json(json!({ "metadata": metadata }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    assert!(contract
+        .view("decimals_frozen", vec![])
+        .await?
+        .json::<bool>()?);
+
+    // Setting the same decimals again is allowed, changing them is not.
+    contract
+        .call("update_metadata")
+        .args_json(json!({ "metadata": metadata }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    let metadata = UpdateFungibleTokenMetadata {
+        decimals: Some(6),
+        ..Default::default()
+    };
+    let result = contract
+        .call("update_metadata")
+        .args_json(json!({ "metadata": metadata }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(result.is_failure());
+
+    // `reference` without `reference_hash` is rejected.
+    let metadata = UpdateFungibleTokenMetadata {
+        reference: Some("https://example.com/token.json".to_string()),
+        ..Default::default()
+    };
+    let result = contract
+        .call("update_metadata")
+        .args_json(json!({ "metadata": metadata }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(result.is_failure());
+
+    // Only the super-admin can override decimals.
+    let account_no_roles = worker.dev_create_account().await?;
+    let result = account_no_roles
+        .call(contract.id(), "override_decimals")
+        .args_json(json!({ "decimals": 6 }))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(result.is_failure());
+    contract
+        .call("override_decimals")
+        .args_json(json!({ "decimals": 6 }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    let metadata: serde_json::Value = contract.view("ft_metadata", vec![]).await?.json()?;
+    assert_eq!(metadata["decimals"], 6);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_near_token_contract_deposit_before_metadata() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let wasm = TokenFactory::compile_token().await?;
+    let contract = worker.dev_deploy(&wasm).await?;
+
+    // Initialize the contract, making the contract itself factory and super-admin.
+    contract
+        .call("new")
+        .args_json(token_new_args(None))
+        .deposit(near_sdk::ONE_NEAR)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    let user = worker.dev_create_account().await?;
+    contract
+        .call("storage_deposit")
+        .args_json(json!({ "account_id": user.id() }))
+        .deposit(near_sdk::ONE_NEAR / 100)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    contract
+        .call("deposit")
+        .args_json(json!({ "receiver_id": user.id(), "amount": "100" }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Minting doesn't freeze decimals, so the first metadata sync still sets them.
+    assert!(!contract
+        .view("decimals_frozen", vec![])
+        .await?
+        .json::<bool>()?);
+    let metadata = UpdateFungibleTokenMetadata {
+        name: Some("Token".to_string()),
+        symbol: Some("TKN".to_string()),
+        decimals: Some(18),
+        ..Default::default()
+    };
+    contract
+        .call("update_metadata")
+        .args_json(json!({ "metadata": metadata }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    let metadata: serde_json::Value = contract.view("ft_metadata", vec![]).await?.json()?;
+    assert_eq!(metadata["symbol"], "TKN");
+    assert_eq!(metadata["decimals"], 18);
+    assert!(contract
+        .view("decimals_frozen", vec![])
+        .await?
+        .json::<bool>()?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_near_token_contract_upgrade_keeps_balances() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let wasm = TokenFactory::compile_token().await?;
+    let contract = worker.dev_deploy(&wasm).await?;
+
+    // Initialize the contract, making the contract itself factory and super-admin.
+    contract
+        .call("new")
+        .args_json(token_new_args(None))
+        .deposit(near_sdk::ONE_NEAR)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    let user = worker.dev_create_account().await?;
+    contract
+        .call("storage_deposit")
+        .args_json(json!({ "account_id": user.id() }))
+        .deposit(near_sdk::ONE_NEAR / 100)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    contract
+        .call("deposit")
+        .args_json(json!({ "receiver_id": user.id(), "amount": "100" }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Only the deployed binary and the factory can call `migrate` directly.
+    let result = user
+        .call(contract.id(), "migrate")
+        .args_json(json!({}))
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(result.into_result().is_err());
+
+    contract
+        .call("upgrade_contract")
+        .args_json(json!({ "binary": base64::encode(&wasm) }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    let balance: String = contract
+        .view(
+            "ft_balance_of",
+            json!({ "account_id": user.id() }).to_string().into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(balance, "100");
+    let total_supply: String = contract.view("ft_total_supply", vec![]).await?.json()?;
+    assert_eq!(total_supply, "100");
+    let state_version: u8 = contract.view("state_version", vec![]).await?.json()?;
+    assert_eq!(state_version, 1);
+
+    // The factory records the version of the upgraded binary.
+    let mut info: serde_json::Value = contract.view("bridged_token_info", vec![]).await?.json()?;
+    info["binary_version"] = json!(2);
+    contract
+        .call("migrate")
+        .args_json(json!({ "bridged_token_info": info }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    let info: serde_json::Value = contract.view("bridged_token_info", vec![]).await?.json()?;
+    assert_eq!(info["binary_version"], 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_near_token_contract_freeze_account() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let wasm = TokenFactory::compile_token().await?;
+    let contract = worker.dev_deploy(&wasm).await?;
+
+    // Initialize the contract, making the contract itself factory and super-admin.
+    contract
+        .call("new")
+        .args_json(token_new_args(None))
         .deposit(near_sdk::ONE_NEAR)
         .max_gas()
         .transact()
         .await?
         .into_result()?;
 
-    // Calling access controlled method from account without role fails.
-    let account_no_roles = worker.dev_create_account().await?;
-    call_access_controlled_method(
-        &account_no_roles,
-        &contract,
-        "update_metadata",
-        json!({ "metadata": UpdateFungibleTokenMetadata::default() }),
-    )
-    .await?
-    .assert_acl_failure();
-
-    // Calling access controlled method from account with permission succeeds.
-    call_access_controlled_method(
-        contract.as_account(),
-        &contract,
-        "update_metadata",
-        json!({ "metadata": UpdateFungibleTokenMetadata::default() }),
-    )
-    .await?
-    .assert_success_unit_return();
-
-    // Calling a method provided by `#[access_controllable]`.
-    assert!(!call_acl_has_role(&contract, "MetadataUpdater", account_no_roles.id()).await?);
-    assert!(call_acl_has_role(&contract, "MetadataUpdater", contract.id()).await?);
+    let compliance = worker.dev_create_account().await?;
+    let user = worker.dev_create_account().await?;
+    contract
+        .call("acl_grant_role")
+        .args_json(json!({ "role": "Compliance", "account_id": compliance.id() }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Register and mint some tokens for the user.
+    for account in [&user, &compliance] {
+        contract
+            .call("storage_deposit")
+            .args_json(json!({ "account_id": account.id() }))
+            .deposit(near_sdk::ONE_NEAR / 100)
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+    }
+    contract
+        .call("deposit")
+        .args_json(json!({ "receiver_id": user.id(), "amount": "100" }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Only compliance can freeze accounts.
+    call_access_controlled_method(
+        &user,
+        &contract,
+        "freeze_account",
+        json!({ "account_id": user.id() }),
+    )
+    .await?
+    .assert_acl_failure();
+    call_access_controlled_method(
+        &compliance,
+        &contract,
+        "freeze_account",
+        json!({ "account_id": user.id() }),
+    )
+    .await?
+    .assert_success_return_value(true);
+    let frozen: Vec<String> = contract
+        .view(
+            "get_frozen_accounts",
+            json!({ "skip": 0, "limit": 10 }).to_string().into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(frozen, vec![user.id().to_string()]);
+
+    // Frozen accounts can't transfer.
+    let result = user
+        .call(contract.id(), "ft_transfer")
+        .args_json(json!({ "receiver_id": compliance.id(), "amount": "10" }))
+        .deposit(1)
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(result.is_failure());
+
+    // Transfers work again after unfreezing.
+    call_access_controlled_method(
+        &compliance,
+        &contract,
+        "unfreeze_account",
+        json!({ "account_id": user.id() }),
+    )
+    .await?
+    .assert_success_return_value(true);
+    user.call(contract.id(), "ft_transfer")
+        .args_json(json!({ "receiver_id": compliance.id(), "amount": "10" }))
+        .deposit(1)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    let balance = nep141_utils::ft_balance_of(&user, contract.id(), compliance.id()).await?;
+    assert_eq!(balance, 10);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_near_token_contract_force_unregister() -> anyhow::Result<()> {
+    let worker = workspaces::sandbox().await?;
+    let wasm = TokenFactory::compile_token().await?;
+    let contract = worker.dev_deploy(&wasm).await?;
+
+    // Initialize the contract, making the contract itself factory and super-admin.
+    contract
+        .call("new")
+        .args_json(token_new_args(None))
+        .deposit(near_sdk::ONE_NEAR)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    let user = worker.dev_create_account().await?;
+    contract
+        .call("storage_deposit")
+        .args_json(json!({ "account_id": user.id() }))
+        .deposit(near_sdk::ONE_NEAR / 100)
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    contract
+        .call("deposit")
+        .args_json(json!({ "receiver_id": user.id(), "amount": "100" }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Without a refund address the balance can't be unregistered, even with `force`.
+    let result = user
+        .call(contract.id(), "storage_unregister")
+        .args_json(json!({ "force": true }))
+        .deposit(1)
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(result.is_failure());
+    let balance = nep141_utils::ft_balance_of(&user, contract.id(), user.id()).await?;
+    assert_eq!(balance, 100);
+
+    // With a refund address the balance is burnt and withdrawn to that address.
+    let refund_address = "000000000000000000000000000000000000000c";
+    contract
+        .call("set_unregister_refund_address")
+        .args_json(json!({ "address": refund_address }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    let result = user
+        .call(contract.id(), "storage_unregister")
+        .args_json(json!({ "force": true }))
+        .deposit(1)
+        .max_gas()
+        .transact()
+        .await?;
+    assert!(result
+        .logs()
+        .iter()
+        .any(|log| log.contains("ft_burn") && log.contains("storage_unregister")));
+    assert!(result.json::<bool>()?);
+
+    let storage_balance: serde_json::Value = contract
+        .view(
+            "storage_balance_of",
+            json!({ "account_id": user.id() }).to_string().into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert!(storage_balance.is_null());
+    let accounting: serde_json::Value = contract.view("bridge_accounting", vec![]).await?.json()?;
+    assert_eq!(accounting["total_supply"], "0");
+    assert_eq!(accounting["total_bridged_out"], "100");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_native_token_connector() {
+    let wnear_mint_amount = 5_000_000_000_000_000_000_000_000_u128;
+    let token_mint_amount = 0x_1000_0000_0000_0000_u128;
+    let token_deposit_amount = 0x_aaaa_bbbb_cccc_u128;
+    let context = NativeTokenConnectorTestContext::new().await.unwrap();
+    let user = context.worker.dev_create_account().await.unwrap();
+    let user_address = aurora_engine_sdk::types::near_account_to_evm_address(user.id().as_bytes());
+
+    // Mint ERC-20 tokens for user in EVM
+    let mint_result = context
+        .engine
+        .call_evm_contract(
+            context.erc20.address,
+            context.erc20.mint(user_address, token_mint_amount.into()),
+            Wei::zero(),
+        )
+        .await
+        .unwrap();
+    aurora_engine_utils::unwrap_success(mint_result.status).unwrap();
+
+    // Mint NEAR for user in EVM (a wNEAR balance is required to deploy a new token)
+    context
+        .engine
+        .mint_wnear(&context.wnear, user_address, wnear_mint_amount)
+        .await
+        .unwrap();
+
+    // Approve locker to take tokens from user
+    let approve_result = context
+        .engine
+        .call_evm_contract_with(
+            &user,
+            context.erc20.address,
+            context
+                .erc20
+                .approve(context.locker.address, token_mint_amount.into()),
+            Wei::zero(),
+        )
+        .await
+        .unwrap();
+    aurora_engine_utils::unwrap_success(approve_result.status).unwrap();
+
+    // Approve locker to take NEAR from user
+    let approve_result = context
+        .engine
+        .call_evm_contract_with(
+            &user,
+            context.wnear.aurora_token.address,
+            context
+                .wnear
+                .aurora_token
+                .approve(context.locker.address, wnear_mint_amount.into()),
+            Wei::zero(),
+        )
+        .await
+        .unwrap();
+    aurora_engine_utils::unwrap_success(approve_result.status).unwrap();
+
+    // Create the token on NEAR
+    let create_result = context
+        .engine
+        .call_evm_contract_with(
+            &user,
+            context.locker.address,
+            context.locker.create_token(context.erc20.address),
+            Wei::zero(),
+        )
+        .await
+        .unwrap();
+    aurora_engine_utils::unwrap_success(create_result.status).unwrap();
+
+    // Confirm token was created using a view call
+    // (if the account was not created the view call would fail because the account does not exist).
+    let token_account = format!(
+        "{}.{}",
+        context.erc20.address.encode(),
+        context.factory.inner.id()
+    )
+    .parse()
+    .unwrap();
+    let balance = nep141_utils::ft_balance_of(&user, &token_account, context.factory.inner.id())
+        .await
+        .unwrap();
+    assert_eq!(balance, 0);
+
+    // The token knows which ERC-20 it represents.
+    let info: serde_json::Value = user
+        .view(&token_account, "bridged_token_info", vec![])
+        .await
+        .unwrap()
+        .json()
+        .unwrap();
+    assert_eq!(info["erc20_address"], context.erc20.address.encode());
+    assert_eq!(info["locker"], context.locker.address.encode());
+    assert_eq!(info["factory"], context.factory.inner.id().as_str());
+
+    // Before a deposit will be accepted, the user must do the storage registration
+    let create_result = context
+        .engine
+        .call_evm_contract_with(
+            &user,
+            context.locker.address,
+            context
+                .locker
+                .storage_deposit(context.erc20.address, user.id()),
+            Wei::zero(),
+        )
+        .await
+        .unwrap();
+    aurora_engine_utils::unwrap_success(create_result.status).unwrap();
+
+    // Deposit tokens into locker
+    let deposit_result = context
+        .engine
+        .call_evm_contract_with(
+            &user,
+            context.locker.address,
+            context
+                .locker
+                .deposit(context.erc20.address, user.id(), token_deposit_amount),
+            Wei::zero(),
+        )
+        .await
+        .unwrap();
+    aurora_engine_utils::unwrap_success(deposit_result.status).unwrap();
+
+    // The deposit call to the locker only schedules, need to actually execute it
+    let locker_near_account = format!(
+        "{}.{}",
+        context.locker.address.encode(),
+        context.engine.inner.id()
+    )
+    .parse()
+    .unwrap();
+    let deposit_outcome = user
+        .call(&locker_near_account, "execute_scheduled")
+        .args_json(serde_json::json!({
+            "nonce": "0",
+        }))
+        .max_gas()
+        .transact()
+        .await
+        .unwrap();
+    deposit_outcome.into_result().unwrap();
+
+    // Verify the balance exists on NEAR now
+    let balance = nep141_utils::ft_balance_of(&user, &token_account, user.id())
+        .await
+        .unwrap();
+    assert_eq!(balance, token_deposit_amount);
+
+    // Verify the tokens have been taken from the user in the EVM
+    let evm_token_balance = context
+        .engine
+        .erc20_balance_of(&context.erc20, user_address)
+        .await
+        .unwrap();
+    assert_eq!(
+        evm_token_balance,
+        (token_mint_amount - token_deposit_amount).into()
+    );
+
+    // Withdraw the tokens from NEAR back to the EVM
+    let withdraw_outcome = user
+        .call(&token_account, "withdraw")
+        .args_json(serde_json::json!({
+            "receiver_id": user_address.encode(),
+            "amount": token_deposit_amount.to_string(),
+        }))
+        .max_gas()
+        .transact()
+        .await
+        .unwrap();
+    withdraw_outcome.into_result().unwrap();
+
+    // Verify the balance removed from NEAR
+    let balance = nep141_utils::ft_balance_of(&user, &token_account, user.id())
+        .await
+        .unwrap();
+    assert_eq!(balance, 0);
+
+    // Lifetime counters reflect both directions
+    let accounting: serde_json::Value = user
+        .view(&token_account, "bridge_accounting", vec![])
+        .await
+        .unwrap()
+        .json()
+        .unwrap();
+    assert_eq!(accounting["total_supply"], "0");
+    assert_eq!(
+        accounting["total_bridged_in"],
+        token_deposit_amount.to_string()
+    );
+    assert_eq!(
+        accounting["total_bridged_out"],
+        token_deposit_amount.to_string()
+    );
+
+    // Verify the tokens have been returned to the user in the EVM
+    let evm_token_balance = context
+        .engine
+        .erc20_balance_of(&context.erc20, user_address)
+        .await
+        .unwrap();
+    assert_eq!(evm_token_balance, token_mint_amount.into());
+}
+
+#[tokio::test]
+async fn test_token_factory_check_reserves() -> anyhow::Result<()> {
+    let context = NativeTokenConnectorTestContext::new().await?;
+    let factory = &context.factory.inner;
+    let (user, token_account) = context.bridge_to_new_account(1_000).await?;
+    let token = context.erc20.address.encode();
+
+    let check_reserves = |account: &workspaces::Account| {
+        account
+            .call(factory.id(), "check_reserves")
+            .args_json(json!({ "token": token }))
+            .max_gas()
+            .transact()
+    };
+
+    // The locker holds exactly the bridged tokens.
+    assert!(check_reserves(&user).await?.json::<bool>()?);
+
+    for role in ["Admin", "PauseManager"] {
+        factory
+            .call("acl_grant_role")
+            .args_json(json!({ "role": role, "account_id": factory.id() }))
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+    }
+
+    // Held withdrawals are burnt on NEAR but still locked on Aurora.
+    factory
+        .call("set_large_withdrawal_config")
+        .args_json(json!({
+            "token": token,
+            "config": { "threshold": "500", "delay_sec": 3600 },
+        }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    user.call(&token_account, "withdraw")
+        .args_json(json!({
+            "receiver_id": "000000000000000000000000000000000000000c",
+            "amount": "600",
+        }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    let outstanding: String = factory
+        .view(
+            "get_outstanding_withdrawals",
+            json!({ "token": token }).to_string().into_bytes(),
+        )
+        .await?
+        .json()?;
+    assert_eq!(outstanding, "600");
+    assert!(check_reserves(&user).await?.json::<bool>()?);
+
+    // Tokens sent to the locker outside of the bridge cause a mismatch.
+    let mint_result = context
+        .engine
+        .call_evm_contract(
+            context.erc20.address,
+            context.erc20.mint(context.locker.address, 1_u64.into()),
+            Wei::zero(),
+        )
+        .await?;
+    aurora_engine_utils::unwrap_success(mint_result.status)?;
+    factory
+        .call("set_auto_pause_on_reserve_mismatch")
+        .args_json(json!({ "enabled": true }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Only checks by pause managers pause the token.
+    let token_paused_args = json!({ "token": token }).to_string().into_bytes();
+    let token_paused = move || factory.view("token_paused", token_paused_args.clone());
+    assert!(!check_reserves(&user).await?.json::<bool>()?);
+    assert_eq!(
+        token_paused().await?.json::<serde_json::Value>()?,
+        json!({ "deposit": false, "withdraw": false })
+    );
+    assert!(!check_reserves(factory.as_account()).await?.json::<bool>()?);
+    assert_eq!(
+        token_paused().await?.json::<serde_json::Value>()?,
+        json!({ "deposit": true, "withdraw": true })
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_token_factory_delegation() -> anyhow::Result<()> {
+    let context = NativeTokenConnectorTestContext::new().await?;
+    let factory = &context.factory.inner;
+    let token = context.erc20.address.encode();
+    let issuer = context.worker.dev_create_account().await?;
+    let updater = context.worker.dev_create_account().await?;
+
+    factory
+        .call("acl_grant_role")
+        .args_json(json!({ "role": "Admin", "account_id": factory.id() }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    factory
+        .call("set_token_delegation")
+        .args_json(json!({
+            "token": token,
+            "delegation": {
+                "super_admin": issuer.id(),
+                "metadata_updaters": [updater.id()],
+            },
+        }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    // The delegation is applied when the token is created.
+    let (_, token_account) = context.bridge_to_new_account(1_000).await?;
+    let viewer = &issuer;
+    let view_acl = |method: &'static str, args: serde_json::Value| {
+        let token_account = token_account.clone();
+        async move {
+            viewer
+                .view(&token_account, method, args.to_string().into_bytes())
+                .await?
+                .json::<bool>()
+        }
+    };
+    assert!(view_acl("acl_is_super_admin", json!({ "account_id": issuer.id() })).await?);
+    assert!(!view_acl("acl_is_super_admin", json!({ "account_id": factory.id() })).await?);
+    assert!(
+        view_acl(
+            "acl_has_role",
+            json!({ "role": "MetadataUpdater", "account_id": updater.id() }),
+        )
+        .await?
+    );
+
+    // The factory can't grant roles once super-admin is delegated.
+    let result = factory
+        .call("grant_token_role")
+        .args_json(json!({
+            "token": token,
+            "role": "MetadataUpdater",
+            "account_id": factory.id(),
+        }))
+        .max_gas()
+        .transact()
+        .await?;
+    let err = format!("{:?}", result.into_result().unwrap_err());
+    assert!(err.contains("ERR_NOT_TOKEN_SUPER_ADMIN"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_token_factory_default_fee_reaches_tokens() -> anyhow::Result<()> {
+    let context = NativeTokenConnectorTestContext::new().await?;
+    let (user, token_account) = context.bridge_to_new_account(1_000).await?;
+    let factory = &context.factory.inner;
+    factory
+        .call("acl_grant_role")
+        .args_json(json!({ "role": "Admin", "account_id": factory.id() }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Changing the collector and the default schedule updates deployed tokens.
+    factory
+        .call("set_fee_collector")
+        .args_json(json!({ "collector_id": user.id() }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    factory
+        .call("set_default_fee_schedule")
+        .args_json(json!({ "schedule": { "fee_bps": 30, "min_fee": "0" } }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    let fee: serde_json::Value = user
+        .view(&token_account, "withdraw_fee", vec![])
+        .await?
+        .json()?;
+    assert_eq!(fee["collector_id"], user.id().as_str());
+    assert_eq!(fee["schedule"]["fee_bps"], 30);
 
     Ok(())
 }
 
 #[tokio::test]
-async fn test_native_token_connector() {
-    let wnear_mint_amount = 5_000_000_000_000_000_000_000_000_u128;
-    let token_mint_amount = 0x_1000_0000_0000_0000_u128;
-    let token_deposit_amount = 0x_aaaa_bbbb_cccc_u128;
-    let context = NativeTokenConnectorTestContext::new().await.unwrap();
-    let user = context.worker.dev_create_account().await.unwrap();
+async fn test_token_factory_deposit_batch() -> anyhow::Result<()> {
+    let context = NativeTokenConnectorTestContext::new().await?;
+    let (user, token_account) = context.bridge_to_new_account(1_000).await?;
     let user_address = aurora_engine_sdk::types::near_account_to_evm_address(user.id().as_bytes());
+    let unregistered: workspaces::AccountId = "unregistered.test.near".parse()?;
 
-    // Mint ERC-20 tokens for user in EVM
     let mint_result = context
         .engine
         .call_evm_contract(
             context.erc20.address,
-            context.erc20.mint(user_address, token_mint_amount.into()),
+            context.erc20.mint(user_address, 600_u128.into()),
             Wei::zero(),
         )
-        .await
-        .unwrap();
-    aurora_engine_utils::unwrap_success(mint_result.status).unwrap();
-
-    // Mint NEAR for user in EVM (a wNEAR balance is required to deploy a new token)
-    context
-        .engine
-        .mint_wnear(&context.wnear, user_address, wnear_mint_amount)
-        .await
-        .unwrap();
-
-    // Approve locker to take tokens from user
+        .await?;
+    aurora_engine_utils::unwrap_success(mint_result.status)?;
     let approve_result = context
         .engine
         .call_evm_contract_with(
@@ -241,148 +1208,315 @@ async fn test_native_token_connector() {
             context.erc20.address,
             context
                 .erc20
-                .approve(context.locker.address, token_mint_amount.into()),
+                .approve(context.locker.address, 600_u128.into()),
             Wei::zero(),
         )
-        .await
-        .unwrap();
-    aurora_engine_utils::unwrap_success(approve_result.status).unwrap();
+        .await?;
+    aurora_engine_utils::unwrap_success(approve_result.status)?;
 
-    // Approve locker to take NEAR from user
+    // The second deposit is rejected by the factory, since it doesn't cover the fee,
+    // and the third fails in the token, since the receiver is not registered.
+    let deposits = [
+        (context.erc20.address, user.id(), 100),
+        (context.erc20.address, user.id(), 0),
+        (context.erc20.address, &unregistered, 200),
+        (context.erc20.address, user.id(), 300),
+    ];
+    let result = context
+        .engine
+        .call_evm_contract_with(
+            &user,
+            context.locker.address,
+            context.locker.deposit_batch(&deposits),
+            Wei::zero(),
+        )
+        .await?;
+    aurora_engine_utils::unwrap_success(result.status)?;
+    user.call(&context.locker_near_account(), "execute_scheduled")
+        .args_json(json!({ "nonce": "1" }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    // Successful deposits are minted and failed ones are refunded on Aurora.
+    let balance = nep141_utils::ft_balance_of(&user, &token_account, user.id()).await?;
+    assert_eq!(balance, 1_400);
+    let evm_balance = context
+        .engine
+        .erc20_balance_of(&context.erc20, user_address)
+        .await?;
+    assert_eq!(evm_balance, 200_u128.into());
+
+    // A batch of the maximum size fits in the gas of a transaction.
+    let mint_result = context
+        .engine
+        .call_evm_contract(
+            context.erc20.address,
+            context.erc20.mint(user_address, 16_u128.into()),
+            Wei::zero(),
+        )
+        .await?;
+    aurora_engine_utils::unwrap_success(mint_result.status)?;
     let approve_result = context
         .engine
         .call_evm_contract_with(
             &user,
-            context.wnear.aurora_token.address,
+            context.erc20.address,
             context
-                .wnear
-                .aurora_token
-                .approve(context.locker.address, wnear_mint_amount.into()),
+                .erc20
+                .approve(context.locker.address, 16_u128.into()),
             Wei::zero(),
         )
-        .await
-        .unwrap();
-    aurora_engine_utils::unwrap_success(approve_result.status).unwrap();
-
-    // Create the token on NEAR
-    let create_result = context
+        .await?;
+    aurora_engine_utils::unwrap_success(approve_result.status)?;
+    let deposits = vec![(context.erc20.address, user.id(), 1); 16];
+    let result = context
         .engine
         .call_evm_contract_with(
             &user,
             context.locker.address,
-            context.locker.create_token(context.erc20.address),
+            context.locker.deposit_batch(&deposits),
             Wei::zero(),
         )
-        .await
-        .unwrap();
-    aurora_engine_utils::unwrap_success(create_result.status).unwrap();
-
-    // Confirm token was created using a view call
-    // (if the account was not created the view call would fail because the account does not exist).
-    let token_account = format!(
-        "{}.{}",
-        context.erc20.address.encode(),
-        context.factory.inner.id()
-    )
-    .parse()
-    .unwrap();
-    let balance = nep141_utils::ft_balance_of(&user, &token_account, context.factory.inner.id())
-        .await
-        .unwrap();
-    assert_eq!(balance, 0);
+        .await?;
+    aurora_engine_utils::unwrap_success(result.status)?;
+    user.call(&context.locker_near_account(), "execute_scheduled")
+        .args_json(json!({ "nonce": "2" }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    let balance = nep141_utils::ft_balance_of(&user, &token_account, user.id()).await?;
+    assert_eq!(balance, 1_416);
 
-    // Before a deposit will be accepted, the user must do the storage registration
-    let create_result = context
+    // Larger batches are rejected by the locker.
+    let deposits = vec![(context.erc20.address, user.id(), 1); 17];
+    let result = context
         .engine
         .call_evm_contract_with(
             &user,
             context.locker.address,
-            context
-                .locker
-                .storage_deposit(context.erc20.address, user.id()),
+            context.locker.deposit_batch(&deposits),
             Wei::zero(),
         )
-        .await
-        .unwrap();
-    aurora_engine_utils::unwrap_success(create_result.status).unwrap();
+        .await?;
+    assert!(aurora_engine_utils::unwrap_success(result.status).is_err());
 
-    // Deposit tokens into locker
-    let deposit_result = context
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_token_factory_deposit_upgrades_stale_token() -> anyhow::Result<()> {
+    let context = NativeTokenConnectorTestContext::new().await?;
+    let (user, token_account) = context.bridge_to_new_account(1_000).await?;
+    let user_address = aurora_engine_sdk::types::near_account_to_evm_address(user.id().as_bytes());
+    let factory = &context.factory.inner;
+
+    // Activate a new binary, so the token runs an outdated one.
+    factory
+        .call("set_token_binary")
+        .args_json(json!({ "binary": base64::encode(TokenFactory::compile_token().await?) }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    factory
+        .call("acl_grant_role")
+        .args_json(json!({ "role": "Admin", "account_id": factory.id() }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+    factory
+        .call("set_lazy_token_upgrades")
+        .args_json(json!({ "enabled": true }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    let mint_result = context
         .engine
-        .call_evm_contract_with(
-            &user,
+        .call_evm_contract(
+            context.erc20.address,
+            context.erc20.mint(user_address, 500_u128.into()),
+            Wei::zero(),
+        )
+        .await?;
+    aurora_engine_utils::unwrap_success(mint_result.status)?;
+    for (address, input) in [
+        (
+            context.erc20.address,
+            context
+                .erc20
+                .approve(context.locker.address, 500_u128.into()),
+        ),
+        (
             context.locker.address,
             context
                 .locker
-                .deposit(context.erc20.address, user.id(), token_deposit_amount),
-            Wei::zero(),
+                .deposit(context.erc20.address, user.id(), 500),
+        ),
+    ] {
+        let result = context
+            .engine
+            .call_evm_contract_with(&user, address, input, Wei::zero())
+            .await?;
+        aurora_engine_utils::unwrap_success(result.status)?;
+    }
+    user.call(&context.locker_near_account(), "execute_scheduled")
+        .args_json(json!({ "nonce": "1" }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
+
+    // The token is upgraded before minting the deposit.
+    let info: serde_json::Value = user
+        .view(&token_account, "bridged_token_info", vec![])
+        .await?
+        .json()?;
+    assert_eq!(info["binary_version"], json!(2));
+    let upgrading: bool = user
+        .view(
+            factory.id(),
+            "is_token_upgrading",
+            json!({ "token_account_id": token_account })
+                .to_string()
+                .into_bytes(),
         )
-        .await
-        .unwrap();
-    aurora_engine_utils::unwrap_success(deposit_result.status).unwrap();
+        .await?
+        .json()?;
+    assert!(!upgrading);
+    let balance = nep141_utils::ft_balance_of(&user, &token_account, user.id()).await?;
+    assert_eq!(balance, 1_500);
 
-    // The deposit call to the locker only schedules, need to actually execute it
-    let locker_near_account = format!(
-        "{}.{}",
-        context.locker.address.encode(),
-        context.engine.inner.id()
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_token_factory_upgrade_baseline_token() -> anyhow::Result<()> {
+    let context = NativeTokenConnectorTestContext::new().await?;
+    let factory = &context.factory.inner;
+    let set_token_binary = move |method: &'static str, wasm: Vec<u8>| async move {
+        factory
+            .call(method)
+            .args_json(json!({ "binary": base64::encode(wasm) }))
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()
+    };
+
+    // Create the token with the first binary, which doesn't migrate its state. It
+    // misses exports required by the factory, so it is set as a legacy binary.
+    set_token_binary(
+        "set_legacy_token_binary",
+        TokenFactory::baseline_token().await?,
     )
-    .parse()
-    .unwrap();
-    let deposit_outcome = user
-        .call(&locker_near_account, "execute_scheduled")
-        .args_json(serde_json::json!({
-            "nonce": "0",
-        }))
+    .await?;
+    let (user, token_account) = context.bridge_to_new_account(1_000).await?;
+
+    set_token_binary("set_token_binary", TokenFactory::compile_token().await?).await?;
+    factory
+        .call("acl_grant_role")
+        .args_json(json!({ "role": "Admin", "account_id": factory.id() }))
         .max_gas()
         .transact()
-        .await
-        .unwrap();
-    deposit_outcome.into_result().unwrap();
+        .await?
+        .into_result()?;
+    factory
+        .call("set_lazy_token_upgrades")
+        .args_json(json!({ "enabled": true }))
+        .max_gas()
+        .transact()
+        .await?
+        .into_result()?;
 
-    // Verify the balance exists on NEAR now
-    let balance = nep141_utils::ft_balance_of(&user, &token_account, user.id())
-        .await
-        .unwrap();
-    assert_eq!(balance, token_deposit_amount);
+    let upgraded: bool = user
+        .call(factory.id(), "upgrade_token")
+        .args_json(json!({ "token_account_id": token_account }))
+        .max_gas()
+        .transact()
+        .await?
+        .json()?;
+    assert!(upgraded);
 
-    // Verify the tokens have been taken from the user in the EVM
-    let evm_token_balance = context
-        .engine
-        .erc20_balance_of(&context.erc20, user_address)
-        .await
-        .unwrap();
+    // The state is converted and the provenance is set by the factory.
+    let view = |method: &'static str, args: serde_json::Value| {
+        let token_account = token_account.clone();
+        let user = user.clone();
+        async move {
+            user.view(&token_account, method, args.to_string().into_bytes())
+                .await?
+                .json::<serde_json::Value>()
+        }
+    };
     assert_eq!(
-        evm_token_balance,
-        (token_mint_amount - token_deposit_amount).into()
+        view("ft_balance_of", json!({ "account_id": user.id() })).await?,
+        json!("1000")
     );
+    assert_eq!(view("state_version", json!({})).await?, json!(1));
+    let info = view("bridged_token_info", json!({})).await?;
+    assert_eq!(info["erc20_address"], context.erc20.address.encode());
+    assert_eq!(info["factory"], factory.id().as_str());
+    assert_eq!(info["binary_version"], json!(3));
+    let accounting = view("bridge_accounting", json!({})).await?;
+    assert_eq!(accounting["total_supply"], json!("1000"));
+    assert_eq!(accounting["total_bridged_in"], json!("1000"));
 
-    // Withdraw the tokens from NEAR back to the EVM
-    let withdraw_outcome = user
-        .call(&token_account, "withdraw")
-        .args_json(serde_json::json!({
-            "receiver_id": user_address.encode(),
-            "amount": token_deposit_amount.to_string(),
+    // The access control state is kept, so the factory is still super-admin.
+    factory
+        .call("grant_token_role")
+        .args_json(json!({
+            "token": context.erc20.address.encode(),
+            "role": "Compliance",
+            "account_id": user.id(),
         }))
         .max_gas()
         .transact()
-        .await
-        .unwrap();
-    withdraw_outcome.into_result().unwrap();
+        .await?
+        .into_result()?;
+    assert_eq!(
+        view(
+            "acl_has_role",
+            json!({ "role": "Compliance", "account_id": user.id() }),
+        )
+        .await?,
+        json!(true)
+    );
+    assert_eq!(
+        view(
+            "acl_has_role",
+            json!({ "role": "MetadataUpdater", "account_id": factory.id() }),
+        )
+        .await?,
+        json!(true)
+    );
 
-    // Verify the balance removed from NEAR
-    let balance = nep141_utils::ft_balance_of(&user, &token_account, user.id())
-        .await
-        .unwrap();
-    assert_eq!(balance, 0);
+    // The token runs the active binary, so it can't be upgraded again.
+    let result = user
+        .call(factory.id(), "upgrade_token")
+        .args_json(json!({ "token_account_id": token_account }))
+        .max_gas()
+        .transact()
+        .await?;
+    let err = format!("{:?}", result.into_result().unwrap_err());
+    assert!(err.contains("ERR_TOKEN_UP_TO_DATE"));
 
-    // Verify the tokens have been returned to the user in the EVM
-    let evm_token_balance = context
-        .engine
-        .erc20_balance_of(&context.erc20, user_address)
-        .await
-        .unwrap();
-    assert_eq!(evm_token_balance, token_mint_amount.into());
+    Ok(())
+}
+
+/// Arguments for `new` of a `near-token-contract` deployed outside of the factory.
+fn token_new_args(super_admin: Option<&str>) -> serde_json::Value {
+    json!({
+        "super_admin": super_admin,
+        "erc20_address": "000000000000000000000000000000000000000b",
+        "aurora": "aurora",
+        "locker": "000000000000000000000000000000000000000a",
+        "binary_version": 1,
+    })
 }
 
 struct NativeTokenConnectorTestContext {
@@ -453,4 +1587,98 @@ impl NativeTokenConnectorTestContext {
             erc20,
         })
     }
+    /// Create the NEP-141 representative of `erc20` and bridge `amount` tokens from
+    /// Aurora to a new NEAR account. Returns the account and the token account id.
+    pub async fn bridge_to_new_account(
+        &self,
+        amount: u128,
+    ) -> anyhow::Result<(workspaces::Account, workspaces::AccountId)> {
+        let wnear_mint_amount = 5_000_000_000_000_000_000_000_000_u128;
+        let user = self.worker.dev_create_account().await?;
+        let user_address =
+            aurora_engine_sdk::types::near_account_to_evm_address(user.id().as_bytes());
+
+        let mint_result = self
+            .engine
+            .call_evm_contract(
+                self.erc20.address,
+                self.erc20.mint(user_address, amount.into()),
+                Wei::zero(),
+            )
+            .await?;
+        aurora_engine_utils::unwrap_success(mint_result.status)?;
+        self.engine
+            .mint_wnear(&self.wnear, user_address, wnear_mint_amount)
+            .await?;
+
+        // Approve locker to take tokens and NEAR from user
+        let approvals = [
+            (
+                self.erc20.address,
+                self.erc20.approve(self.locker.address, amount.into()),
+            ),
+            (
+                self.wnear.aurora_token.address,
+                self.wnear
+                    .aurora_token
+                    .approve(self.locker.address, wnear_mint_amount.into()),
+            ),
+        ];
+        for (address, input) in approvals {
+            let result = self
+                .engine
+                .call_evm_contract_with(&user, address, input, Wei::zero())
+                .await?;
+            aurora_engine_utils::unwrap_success(result.status)?;
+        }
+
+        // Create the token and register the user
+        for input in [
+            self.locker.create_token(self.erc20.address),
+            self.locker.storage_deposit(self.erc20.address, user.id()),
+        ] {
+            let result = self
+                .engine
+                .call_evm_contract_with(&user, self.locker.address, input, Wei::zero())
+                .await?;
+            aurora_engine_utils::unwrap_success(result.status)?;
+        }
+
+        // Deposit tokens into locker, the call is only scheduled so it is executed next
+        let deposit_result = self
+            .engine
+            .call_evm_contract_with(
+                &user,
+                self.locker.address,
+                self.locker.deposit(self.erc20.address, user.id(), amount),
+                Wei::zero(),
+            )
+            .await?;
+        aurora_engine_utils::unwrap_success(deposit_result.status)?;
+        user.call(&self.locker_near_account(), "execute_scheduled")
+            .args_json(json!({ "nonce": "0" }))
+            .max_gas()
+            .transact()
+            .await?
+            .into_result()?;
+
+        let token_account = format!(
+            "{}.{}",
+            self.erc20.address.encode(),
+            self.factory.inner.id()
+        )
+        .parse()?;
+        Ok((user, token_account))
+    }
+
+    /// NEAR account of the locker, which schedules the calls made by the locker.
+    pub fn locker_near_account(&self) -> workspaces::AccountId {
+        format!(
+            "{}.{}",
+            self.locker.address.encode(),
+            self.engine.inner.id()
+        )
+        .parse()
+        .unwrap()
+    }
 }
@@ -87,6 +87,15 @@ impl TokenFactory {
         Ok(bytes)
     }
 
+    /// First token binary, built from the initial revision of `near-token-contract`.
+    /// Tokens deployed with it don't store a state version nor migrate their state
+    /// when upgraded.
+    pub async fn baseline_token() -> anyhow::Result<Vec<u8>> {
+        let res = Path::new("res");
+        let bytes = tokio::fs::read(res.join("near_token_contract_v0.wasm")).await?;
+        Ok(bytes)
+    }
+
     pub async fn compile_token() -> anyhow::Result<Vec<u8>> {
         let root_path = Path::new(ROOT_PATH);
         add_wasm_target(root_path).await?;